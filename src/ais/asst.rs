@@ -1,32 +1,49 @@
 use std::collections::{HashMap, HashSet};
+use std::future::Future;
 use std::path::Path;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use async_openai::types::{
     CreateAssistantRequest, 
     AssistantToolsRetrieval, 
+    AssistantToolsFunction,
+    AssistantTools,
     AssistantObject, 
+    FunctionObject,
+    RequiredAction,
+    SubmitToolOutputsRunRequest,
+    ToolsOutputs,
     ModifyAssistantRequest, 
     CreateThreadRequest, 
     ThreadObject, 
     CreateRunRequest, 
     RunStatus, CreateFileRequest, CreateAssistantFileRequest,
+    OpenAIFile, MessageObject, MessageRole,
 };
+use async_openai::config::Config;
 use console::Term;
 use derive_more::{From, Deref, Display};
+use futures::StreamExt;
+use reqwest::StatusCode;
 use serde::{Serialize, Deserialize};
+use serde_json::Value;
 use tokio::time::sleep;
 
-use crate::Result;
+use crate::{Error, Result};
 use crate::ais::OaClient;
-use crate::ais::msg::{user_msg, get_text_content};
+use crate::ais::backend::RunMsg;
+use crate::ais::msg::{user_msg, get_text_content, get_text_delta};
+use crate::ais::retry::{retry_after_from_headers, retry_after_from_msg};
+use crate::ais::sse::SseParser;
 use crate::utils::cli::{ico_deleted_ok, ico_check, ico_err, ico_uploading, ico_uploaded};
 use crate::utils::files::XFile;
+use crate::tools::Tools;
 
 // region:    --- Constants
 
 const DEFAULT_QUERY: &[(&str, &str)] = &[("limit", "100")];
-const POLLING_DURATION_MS: u64 = 500;
+const CANCEL_WAIT_POLL_MS: u64 = 250;
+const CANCEL_WAIT_MAX_POLLS: usize = 20;
 
 // endregion: --- Constants
 
@@ -35,11 +52,40 @@ const POLLING_DURATION_MS: u64 = 500;
 pub struct CreateConfig {
     pub name: String,
     pub model: String,
+    /// Function tools (in addition to the retrieval)
+    pub functions: Vec<FunctionObject>,
 }
 
-#[derive(Debug, From, Deref, Display)]
+/// Polling schedule of the runs (`[polling]` table of `rusty_ai.toml`).
+/// The delay starts at `initial_ms` and grows by 50% at each poll, up to `max_ms`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PollPolicy {
+    pub initial_ms: u64,
+    pub max_ms: u64,
+}
+
+impl Default for PollPolicy {
+    fn default() -> Self {
+        Self {
+            initial_ms: 250,
+            max_ms: 2000,
+        }
+    }
+}
+
+impl PollPolicy {
+    fn next_delay(&self, delay: Duration) -> Duration {
+        delay.mul_f32(1.5).min(Duration::from_millis(self.max_ms))
+    }
+}
+
+#[derive(Debug, Clone, From, Deref, Display)]
 pub struct AsstId(String);
 
+/// Callback receiving the streamed text deltas.
+pub type OnDelta<'a> = dyn FnMut(&str) + Send + 'a;
+
 #[derive(Debug, From, Deref, Display, Serialize, Deserialize)]
 pub struct ThreadId(String);
 
@@ -52,52 +98,91 @@ pub struct FileId(String);
 
 pub async fn create(oac: &OaClient, config: CreateConfig) -> Result<AsstId> {
     let oa_assts = oac.assistants();
-    
-    let asst_obj = oa_assts
-    .create(CreateAssistantRequest {
+    let request = CreateAssistantRequest {
         model: config.model,
         name: Some(config.name),
-        tools: Some(vec![AssistantToolsRetrieval::default().into()]),
+        tools: Some(asst_tools(config.functions)),
         ..Default::default()
+    };
+
+    let asst_obj = oac.retry().run_once(|| async {
+        Ok(oa_assts.create(request.clone()).await?)
     }).await?;
     
     Ok(asst_obj.id.into())
 }
 
+/// Returns the retrieval tool and the `functions` tools.
+fn asst_tools(functions: Vec<FunctionObject>) -> Vec<AssistantTools> {
+    let mut tools = vec![AssistantToolsRetrieval::default().into()];
+    tools.extend(functions.into_iter().map(|function| {
+        AssistantToolsFunction {
+            function,
+            ..Default::default()
+        }
+        .into()
+    }));
+
+    tools
+}
+
 pub async fn load_or_create_asst(
     oac: &OaClient,
     config: CreateConfig,
     recreate: bool,
 ) -> Result<AsstId> {
-    let asst_obj = first_by_name(oac, &config.name).await?;
-
-    let mut asst_id = asst_obj.map(|o| AsstId::from(o.id));
-
-    // -- Delete as if recreate true and asst_id
-    if let (true, Some(asst_id_ref)) = (recreate, asst_id.as_ref()) {
-        delete(oac, asst_id_ref).await?;
-        asst_id.take();
-
-        println!("{} Assistant {} deleted", ico_deleted_ok(), config.name);
+    // Note: when recreate, the previous one is deleted by the caller (once the new one ready).
+    let asst_obj = if recreate { None } else { first_by_name(oac, &config.name).await? };
+
+    // -- Update the tools of a loaded assistant if they changed
+    if let Some(asst_obj) = asst_obj.as_ref() {
+        let tools = asst_tools(config.functions.clone());
+        if asst_obj.tools != tools {
+            update_tools(oac, asst_obj, tools).await?;
+            eprintln!("{} Assistant {} tools updated", ico_check(), config.name);
+        }
     }
 
+    let asst_id = asst_obj.map(|o| AsstId::from(o.id));
+
     // -- Create if needed
     if let Some(asst_id) = asst_id {
-        println!("{} Assisted {} loaded", ico_check(), config.name);
+        eprintln!("{} Assisted {} loaded", ico_check(), config.name);
         Ok(asst_id)
     } else {
         let asst_name = config.name.clone();
         let asst_id = create(oac, config).await?;
-        println!("{} Assistant {} created", ico_check(), asst_name);
+        eprintln!("{} Assistant {} created", ico_check(), asst_name);
         Ok(asst_id)
     }
 
 }
 
-pub async fn first_by_name(oac: &OaClient, name: &str) -> Result<Option<AssistantObject>> {
+/// Returns all the assistants (newest first, all the pages).
+pub async fn list(oac: &OaClient) -> Result<Vec<AssistantObject>> {
     let ao_assts = oac.assistants();
 
-    let assts = ao_assts.list(DEFAULT_QUERY).await?.data;
+    let mut assts = Vec::new();
+    let mut after: Option<String> = None;
+    loop {
+        let mut query = vec![("limit", "100")];
+        if let Some(after) = after.as_deref() {
+            query.push(("after", after));
+        }
+        let res = oac.retry().run(|| async { Ok(ao_assts.list(&query).await?) }).await?;
+
+        assts.extend(res.data);
+        match res.last_id {
+            Some(last_id) if res.has_more => after = Some(last_id),
+            _ => break,
+        }
+    }
+
+    Ok(assts)
+}
+
+pub async fn first_by_name(oac: &OaClient, name: &str) -> Result<Option<AssistantObject>> {
+    let assts = list(oac).await?;
 
     let ass_obj = assts
         .into_iter()
@@ -113,7 +198,28 @@ pub async fn upload_instructions(oac: &OaClient, asst_id: &AsstId, inst_content:
         ..Default::default()
     };
 
-    oa_assts.update(asst_id, modify).await?;
+    oac.retry().run(|| async {
+        Ok(oa_assts.update(asst_id, modify.clone()).await?)
+    }).await?;
+
+    Ok(())
+}
+
+async fn update_tools(
+    oac: &OaClient,
+    asst_obj: &AssistantObject,
+    tools: Vec<AssistantTools>,
+) -> Result<()> {
+    let oa_assts = oac.assistants();
+    let modify = ModifyAssistantRequest {
+        model: asst_obj.model.clone(),
+        tools: Some(tools),
+        ..Default::default()
+    };
+
+    oac.retry().run(|| async {
+        Ok(oa_assts.update(&asst_obj.id, modify.clone()).await?)
+    }).await?;
 
     Ok(())
 }
@@ -124,16 +230,16 @@ pub async fn delete(oac: &OaClient, asst_id: &AsstId) -> Result<()> {
 
     // -- First delete the files associated to this assistant
     for file_id in get_files_hashmap(oac, asst_id).await?.into_values() {
-        let del_res = oa_files.delete(&file_id).await;
+        let del_res = oac.retry().run(|| async { Ok(oa_files.delete(&file_id).await?) }).await;
         // Note: might be already deleted, that's ok for now.
         if del_res.is_ok() {
-            println!("{} file deleted - {file_id}", ico_deleted_ok());
+            eprintln!("{} file deleted - {file_id}", ico_deleted_ok());
         }
     }
 
     // Note: No need to delete assistant files since we delete the assistant.
     // -- Delete assistant
-    oa_assts.delete(asst_id).await?;
+    oac.retry().run(|| async { Ok(oa_assts.delete(asst_id).await?) }).await?;
 
     Ok(())
 }
@@ -146,48 +252,115 @@ pub async fn delete(oac: &OaClient, asst_id: &AsstId) -> Result<()> {
 pub async fn create_thred(oac: &OaClient) -> Result<ThreadId> {
     let oa_threads = oac.threads();
 
-    let res = oa_threads
-        .create(CreateThreadRequest {
-            ..Default::default()
-        }).await?;
+    let res = oac.retry().run_once(|| async {
+        Ok(oa_threads.create(CreateThreadRequest::default()).await?)
+    }).await?;
 
     Ok(res.id.into())
         
 }
 
+/// Returns the thread (`Error::ThreadNotFound` if it does not exist anymore).
 pub async fn get_thread(oac: &OaClient, thread_id: &ThreadId) -> Result<ThreadObject> {
     let oa_threads = oac.threads();
 
-    let thread_obj = oa_threads.retrieve(thread_id).await?;
+    let res = oac.retry().run(|| async {
+        Ok(oa_threads.retrieve(thread_id).await?)
+    }).await;
+
+    // Note: the 404 status is dropped by async-openai, only its message tells.
+    match res {
+        Err(Error::Api { message, .. }) if message.starts_with("No thread found") => {
+            Err(Error::ThreadNotFound { thread_id: thread_id.to_string() })
+        }
+        res => res,
+    }
+}
+
+pub async fn delete_thread(oac: &OaClient, thread_id: &ThreadId) -> Result<()> {
+    let oa_threads = oac.threads();
+
+    oac.retry().run(|| async { Ok(oa_threads.delete(thread_id).await?) }).await?;
 
-    Ok(thread_obj)
+    Ok(())
 }
 
+/// Runs the thread with `msg`, polling the run status with the `poll` schedule.
+/// - The function calls of the run are answered with the local `tools`.
+/// - Ctrl-C cancels the run if already created (`Error::Interrupted`).
+/// - The run is cancelled when not done after `timeout` (`Error::RunTimeout`).
 pub async fn run_thread_msg(
     oac: &OaClient, 
     asst_id: &AsstId, 
     thread_id: &ThreadId, 
-    msg: &str
+    msg: RunMsg<'_>,
+    tools: &Tools,
+    poll: &PollPolicy,
+    timeout: Duration,
 ) -> Result<String> {
-    let msg = user_msg(msg);
+    let oa_threads = oac.threads();
+
+    // Note: one Ctrl-C future for all the steps, from the message on.
+    let interrupt = tokio::signal::ctrl_c();
+    tokio::pin!(interrupt);
 
     // -- Attach message to thread
-    let _message_obj = oac.threads().messages(thread_id).create(msg).await?;
+    tokio::select! {
+        res = add_user_msg(oac, thread_id, msg) => res?,
+        _ = &mut interrupt => {
+            Term::stderr().write_str("\n")?;
+            return Err(Error::Interrupted);
+        }
+    }
 
     // -- Create a run for the thread
     let run_request = CreateRunRequest {
         assistant_id: asst_id.to_string(),
         ..Default::default()
     };
-    let run = oac.threads().runs(thread_id).create(run_request).await?;
+    let oa_runs = oa_threads.runs(thread_id);
+    let run = tokio::select! {
+        run = oac.retry().run_once(|| async {
+            Ok(oa_runs.create(run_request.clone()).await?)
+        }) => run?,
+        _ = &mut interrupt => {
+            Term::stderr().write_str("\n")?;
+            // Note: the run may be created without its id known yet.
+            cancel_active_runs(oac, thread_id).await?;
+            return Err(Error::Interrupted);
+        }
+    };
 
-    // -- Loop to get result
-    let term = Term::stdout();
+    // -- Poll until done or Ctrl-C
+    tokio::select! {
+        res = poll_run(oac, thread_id, &run.id, tools, poll, timeout) => res,
+        _ = &mut interrupt => {
+            Term::stderr().write_str("\n")?;
+            cancel_run(oac, thread_id, Some(&run.id)).await?;
+            Err(Error::Interrupted)
+        }
+    }
+}
+
+/// Polls the run until done, and returns the answer.
+async fn poll_run(
+    oac: &OaClient,
+    thread_id: &ThreadId,
+    run_id: &str,
+    tools: &Tools,
+    poll: &PollPolicy,
+    timeout: Duration,
+) -> Result<String> {
+    let oa_threads = oac.threads();
+    let oa_runs = oa_threads.runs(thread_id);
+    let term = Term::stderr();
+    let deadline = Instant::now() + timeout;
+    let mut delay = Duration::from_millis(poll.initial_ms);
 
     loop {
         term.write_str("♲")?;
         // -- Make the request to get the status
-        let run = oac.threads().runs(thread_id).retrieve(&run.id).await?;
+        let run = oac.retry().run(|| async { Ok(oa_runs.retrieve(run_id).await?) }).await?;
         term.write_str("♻︎")?;
         match run.status {
             RunStatus::Completed => {
@@ -195,31 +368,386 @@ pub async fn run_thread_msg(
                 return get_first_thread_msg_content(oac, thread_id).await;
             }
             RunStatus::Queued | RunStatus::InProgress => (),
-            other => {
+            RunStatus::RequiresAction => {
+                term.write_str("\n")?;
+                let outputs = tool_outputs(tools, run.required_action.as_ref());
+                let Ok(outputs) = tokio::time::timeout_at(deadline.into(), outputs).await else {
+                    cancel_run(oac, thread_id, Some(run_id)).await?;
+                    return Err(Error::RunTimeout { timeout });
+                };
+                if outputs.is_empty() {
+                    cancel_run(oac, thread_id, Some(run_id)).await?;
+                    return Err(Error::NoToolOutputs);
+                }
+                let request = SubmitToolOutputsRunRequest { tool_outputs: outputs };
+                oac.retry().run_once(|| async {
+                    Ok(oa_runs.submit_tool_outputs(run_id, request.clone()).await?)
+                }).await?;
+                delay = Duration::from_millis(poll.initial_ms);
+            }
+            status => {
                 term.write_str("\n")?;
-                return Err(format!("ERROR WHILE RUN: {:?}", other).into());
+                return Err(Error::RunFailed {
+                    status,
+                    last_error: run.last_error.map(|e| e.message),
+                });
+            }
+        }
+
+        // -- Cancel the run when out of time
+        let now = Instant::now();
+        if now >= deadline {
+            term.write_str("\n")?;
+            cancel_run(oac, thread_id, Some(run_id)).await?;
+            return Err(Error::RunTimeout { timeout });
+        }
+
+        sleep(delay.min(deadline - now)).await;
+        delay = poll.next_delay(delay);
+    }
+}
+
+/// Same as `run_thread_msg`, but streams the run, calling `on_delta` with each
+/// text delta as it arrives.
+/// - Ctrl-C while streaming cancels the run and returns the partial text.
+/// - The run is cancelled when not done after `timeout` (`Error::RunTimeout`).
+///
+/// Returns `(response_text, has_been_interrupted)`
+pub async fn run_thread_msg_stream(
+    oac: &OaClient,
+    asst_id: &AsstId,
+    thread_id: &ThreadId,
+    msg: RunMsg<'_>,
+    tools: &Tools,
+    timeout: Duration,
+    on_delta: &mut OnDelta<'_>,
+) -> Result<(String, bool)> {
+    // -- Attach message to thread
+    add_user_msg(oac, thread_id, msg).await?;
+
+    // -- Create a streamed run for the thread
+    let run_request = CreateRunRequest {
+        assistant_id: asst_id.to_string(),
+        ..Default::default()
+    };
+    let res = post_stream(oac, &format!("/threads/{thread_id}/runs"), run_request).await?;
+
+    stream_run_events(oac, thread_id, res, tools, timeout, on_delta, tokio::signal::ctrl_c()).await
+}
+
+/// Handles the events of the streamed run `res` until its end (see
+/// `run_thread_msg_stream`), or until `interrupt` (e.g., Ctrl-C).
+async fn stream_run_events(
+    oac: &OaClient,
+    thread_id: &ThreadId,
+    res: reqwest::Response,
+    tools: &Tools,
+    timeout: Duration,
+    on_delta: &mut OnDelta<'_>,
+    interrupt: impl Future,
+) -> Result<(String, bool)> {
+    // -- Loop on the events until run end or interrupt
+    let mut stream = res.bytes_stream();
+    let mut parser = SseParser::default();
+    let mut run_id: Option<String> = None;
+    let mut text = String::new();
+
+    tokio::pin!(interrupt);
+    let out_of_time = sleep(timeout);
+    tokio::pin!(out_of_time);
+
+    loop {
+        let chunk = tokio::select! {
+            chunk = stream.next() => chunk,
+            _ = &mut interrupt => {
+                cancel_stream_run(oac, thread_id, run_id.as_deref()).await?;
+                return Ok((text, true));
+            }
+            _ = &mut out_of_time => {
+                cancel_stream_run(oac, thread_id, run_id.as_deref()).await?;
+                return Err(Error::RunTimeout { timeout });
+            }
+        };
+
+        let Some(chunk) = chunk else {
+            return Err(Error::RunStreamEnded);
+        };
+
+        let mut action = None;
+        for event in parser.push(&chunk?) {
+            match event.event.as_str() {
+                "thread.run.created" => {
+                    let run: Value = serde_json::from_str(&event.data)?;
+                    run_id = run["id"].as_str().map(String::from);
+                }
+                // -- The run continues in the stream of the tool outputs submit.
+                "thread.run.requires_action" => {
+                    let run: Value = serde_json::from_str(&event.data)?;
+                    let required: RequiredAction = serde_json::from_value(run["required_action"].clone())?;
+                    let run_id = run["id"].as_str().unwrap_or_default().to_string();
+                    action = Some((run_id, required));
+                    break;
+                }
+                "thread.message.delta" => {
+                    let delta: Value = serde_json::from_str(&event.data)?;
+                    if let Some(delta) = get_text_delta(&delta) {
+                        on_delta(&delta);
+                        text.push_str(&delta);
+                    }
+                }
+                "thread.run.completed" => return Ok((text, false)),
+                "thread.run.failed" | "thread.run.cancelled" | "thread.run.expired" => {
+                    let run: Value = serde_json::from_str(&event.data)?;
+                    return Err(Error::RunFailed {
+                        status: serde_json::from_value(run["status"].clone())?,
+                        last_error: run["last_error"]["message"].as_str().map(String::from),
+                    });
+                }
+                "error" => {
+                    return Err(Error::Api {
+                        status: None,
+                        message: get_error_message(&event.data),
+                    });
+                }
+                _ => (),
+            }
+        }
+
+        if let Some((run_id, action)) = action {
+            let tool_outputs = tokio::select! {
+                outputs = tool_outputs(tools, Some(&action)) => outputs,
+                _ = &mut interrupt => {
+                    cancel_run(oac, thread_id, Some(&run_id)).await?;
+                    return Ok((text, true));
+                }
+                _ = &mut out_of_time => {
+                    cancel_run(oac, thread_id, Some(&run_id)).await?;
+                    return Err(Error::RunTimeout { timeout });
+                }
+            };
+            if tool_outputs.is_empty() {
+                cancel_run(oac, thread_id, Some(&run_id)).await?;
+                return Err(Error::NoToolOutputs);
             }
+            let request = SubmitToolOutputsRunRequest { tool_outputs };
+            let path = format!("/threads/{thread_id}/runs/{run_id}/submit_tool_outputs");
+            stream = post_stream(oac, &path, request).await?.bytes_stream();
+            parser = SseParser::default();
         }
+    }
+}
+
+/// Returns the outputs of the local `tools` for the function calls of the
+/// required action of a run (none to submit if no function call).
+async fn tool_outputs(tools: &Tools, action: Option<&RequiredAction>) -> Vec<ToolsOutputs> {
+    let tool_calls = action
+        .into_iter()
+        .flat_map(|action| action.submit_tool_outputs.tool_calls.iter())
+        .filter(|call| call.r#type == "function");
+
+    let mut outputs = Vec::new();
+    for call in tool_calls {
+        outputs.push(ToolsOutputs {
+            tool_call_id: Some(call.id.clone()),
+            output: Some(tools.call(&call.function.name, &call.function.arguments).await),
+        });
+    }
+
+    outputs
+}
+
+/// Posts `request` with `"stream": true` to the API `path`, and returns the
+/// SSE response.
+/// Note: async-openai does not support streamed runs, so raw request.
+async fn post_stream(
+    oac: &OaClient,
+    path: &str,
+    request: impl Serialize,
+) -> Result<reqwest::Response> {
+    let config = oac.config();
+    let mut body = serde_json::to_value(request)?;
+    body["stream"] = true.into();
+
+    oac.retry().run_once(|| async {
+        let res = oac
+            .http()
+            .post(config.url(path))
+            .headers(config.headers())
+            .json(&body)
+            .send()
+            .await?;
+        error_for_status(res).await
+    }).await
+}
 
-        sleep(Duration::from_millis(POLLING_DURATION_MS)).await;
+/// Cancels the runs of the thread still active (e.g., left by a killed process),
+/// as they block the new messages.
+///
+/// Returns the number of cancelled runs.
+pub async fn cancel_active_runs(oac: &OaClient, thread_id: &ThreadId) -> Result<usize> {
+    let oa_threads = oac.threads();
+    let oa_runs = oa_threads.runs(thread_id);
+    let runs = oac.retry().run(|| async { Ok(oa_runs.list(DEFAULT_QUERY).await?.data) }).await?;
+
+    let mut num_cancelled = 0;
+    for run in runs {
+        if is_run_active(&run.status) {
+            cancel_run(oac, thread_id, Some(&run.id)).await?;
+            num_cancelled += 1;
+        }
     }
 
+    Ok(num_cancelled)
+}
+
+/// True while the run blocks the thread (including `cancelling`).
+fn is_run_active(status: &RunStatus) -> bool {
+    matches!(
+        status,
+        RunStatus::Queued | RunStatus::InProgress | RunStatus::RequiresAction | RunStatus::Cancelling
+    )
+}
+
+/// Cancels the streamed run, the active one of the thread when its id is not
+/// known yet (interrupted before the `thread.run.created` event).
+async fn cancel_stream_run(oac: &OaClient, thread_id: &ThreadId, run_id: Option<&str>) -> Result<()> {
+    match run_id {
+        Some(run_id) => cancel_run(oac, thread_id, Some(run_id)).await,
+        None => cancel_active_runs(oac, thread_id).await.map(|_| ()),
+    }
+}
+
+/// Cancels the run (if already created), and waits for its end
+/// (no message can be added to the thread until then).
+async fn cancel_run(oac: &OaClient, thread_id: &ThreadId, run_id: Option<&str>) -> Result<()> {
+    let Some(run_id) = run_id else {
+        return Ok(());
+    };
+
+    let oa_threads = oac.threads();
+    let oa_runs = oa_threads.runs(thread_id);
+    let mut run = oac.retry().run(|| async { Ok(oa_runs.cancel(run_id).await?) }).await?;
+
+    for _ in 0..CANCEL_WAIT_MAX_POLLS {
+        if !is_run_active(&run.status) {
+            return Ok(());
+        }
+        sleep(Duration::from_millis(CANCEL_WAIT_POLL_MS)).await;
+        run = oac.retry().run(|| async { Ok(oa_runs.retrieve(run_id).await?) }).await?;
+    }
+
+    Err(Error::RunNotCancelled { run_id: run_id.to_string() })
+}
+
+/// Adds the user message to the thread, unless a retry and already the last
+/// message of the thread (i.e., its run failed).
+async fn add_user_msg(oac: &OaClient, thread_id: &ThreadId, msg: RunMsg<'_>) -> Result<()> {
+    if msg.retry && is_last_user_msg(oac, thread_id, msg).await? {
+        return Ok(());
+    }
+
+    create_user_msg(oac, thread_id, msg.content).await
+}
+
+async fn is_last_user_msg(oac: &OaClient, thread_id: &ThreadId, msg: RunMsg<'_>) -> Result<bool> {
+    static QUERY: [(&str, &str); 1] = [("limit", "1")];
+
+    let oa_threads = oac.threads();
+    let oa_msgs = oa_threads.messages(thread_id);
+    let messages = oac.retry().run(|| async { Ok(oa_msgs.list(&QUERY).await?) }).await?;
+    let Some(last) = messages.data.into_iter().next() else {
+        return Ok(false);
+    };
+
+    Ok(matches!(last.role, MessageRole::User) && msg.is_same_msg(&get_text_content(last)?))
+}
+
+/// Adds the user message to the thread.
+async fn create_user_msg(oac: &OaClient, thread_id: &ThreadId, msg: &str) -> Result<()> {
+    let oa_threads = oac.threads();
+    let oa_msgs = oa_threads.messages(thread_id);
+
+    oac.retry().run_once(|| async { Ok(oa_msgs.create(user_msg(msg)).await?) }).await?;
+
+    Ok(())
+}
+
+/// Returns the response if successful, otherwise its error
+/// (`RateLimited` with the retry-after hint for a 429).
+async fn error_for_status(res: reqwest::Response) -> Result<reqwest::Response> {
+    let status = res.status();
+    if status.is_success() {
+        return Ok(res);
+    }
+
+    let retry_after = retry_after_from_headers(res.headers());
+    let body = res.text().await.unwrap_or_default();
+    let message = get_error_message(&body);
+
+    // Note: 429 is also the status of "insufficient_quota" (not worth a retry).
+    if status == StatusCode::TOO_MANY_REQUESTS && !body.contains("insufficient_quota") {
+        Err(Error::RateLimited {
+            retry_after: retry_after.or_else(|| retry_after_from_msg(&message)),
+            message,
+        })
+    } else if status == StatusCode::UNAUTHORIZED {
+        Err(Error::Auth { message })
+    } else {
+        Err(Error::Api {
+            status: Some(status.as_u16()),
+            message,
+        })
+    }
+}
+
+/// Returns the `error.message` of an API error body (the body if none).
+fn get_error_message(body: &str) -> String {
+    serde_json::from_str::<Value>(body)
+        .ok()
+        .and_then(|v| v["error"]["message"].as_str().map(String::from))
+        .unwrap_or_else(|| body.to_string())
 }
 
 pub async fn get_first_thread_msg_content(oac: &OaClient, thread_id: &ThreadId) -> Result<String> {
     static  QUERY: [(&str, &str); 1] = [("limit", "1")];
 
-    let messages = oac.threads().messages(thread_id).list(&QUERY).await?;
+    let oa_threads = oac.threads();
+    let oa_msgs = oa_threads.messages(thread_id);
+    let messages = oac.retry().run(|| async { Ok(oa_msgs.list(&QUERY).await?) }).await?;
     let msg = messages
         .data
         .into_iter()
         .next()
-        .ok_or_else(|| "No message found".to_string())?;
+        .ok_or(Error::NoMessage)?;
 
     let text = get_text_content(msg)?;
 
     Ok(text)
 }
+
+/// Returns all the messages of the thread (oldest first).
+pub async fn list_thread_msgs(oac: &OaClient, thread_id: &ThreadId) -> Result<Vec<MessageObject>> {
+    let oa_threads = oac.threads();
+    let oa_msgs = oa_threads.messages(thread_id);
+
+    let mut msgs = Vec::new();
+    let mut after: Option<String> = None;
+    loop {
+        let mut query = vec![("limit", "100"), ("order", "asc")];
+        if let Some(after) = after.as_deref() {
+            query.push(("after", after));
+        }
+        let res = oac.retry().run(|| async { Ok(oa_msgs.list(&query).await?) }).await?;
+
+        msgs.extend(res.data);
+        match res.last_id {
+            Some(last_id) if res.has_more => after = Some(last_id),
+            _ => break,
+        }
+    }
+
+    Ok(msgs)
+}
 // endregion: --- Thread
 
 // region:    --- Files
@@ -251,8 +779,9 @@ pub async fn upload_file_by_name(
     if let Some(file_id) = file_id {
         // -- Delete the org file
         let oa_files = oac.files();
-        if let Err(err) = oa_files.delete(&file_id).await {
-            println!(
+        let del_res = oac.retry().run(|| async { Ok(oa_files.delete(&file_id).await?) }).await;
+        if let Err(err) = del_res {
+            eprintln!(
                 "{} Can't delete file '{}'\n     cause: {}",
                 ico_err(),
                 file.to_string_lossy(),
@@ -263,8 +792,11 @@ pub async fn upload_file_by_name(
         // -- Delete the asst_file association
         let oa_assts = oac.assistants();
         let oa_assts_files = oa_assts.files(asst_id);
-        if let Err(err) = oa_assts_files.delete(&file_id).await {
-            println!(
+        let del_res = oac.retry().run(|| async {
+            Ok(oa_assts_files.delete(&file_id).await?)
+        }).await;
+        if let Err(err) = del_res {
+            eprintln!(
                 "{} Can't remove assistant file '{}'\n     cause: {}",
                 ico_err(),
                 file.x_file_name(),
@@ -274,7 +806,7 @@ pub async fn upload_file_by_name(
     }
 
     // -- Upload and attach the file.
-    let term = Term::stdout();
+    let term = Term::stderr();
 
     // Print uploading.
     term.write_line(&format!(
@@ -285,12 +817,12 @@ pub async fn upload_file_by_name(
 
     // Upload file.
     let oa_files = oac.files();
-    let oa_file = oa_files 
-        .create(CreateFileRequest {
+    let oa_file = oac.retry().run_once(|| async {
+        Ok(oa_files.create(CreateFileRequest {
             file: file.into(),
             purpose: "assistants".into(),
-        })
-        .await?;
+        }).await?)
+    }).await?;
 
     // Update print.
     term.clear_last_lines(1)?;
@@ -303,15 +835,15 @@ pub async fn upload_file_by_name(
     // Attach file to assistant.
     let oa_assts = oac.assistants();
     let oa_assts_files = oa_assts.files(asst_id);
-    let asst_file_obj = oa_assts_files
-        .create(CreateAssistantFileRequest {
+    let asst_file_obj = oac.retry().run_once(|| async {
+        Ok(oa_assts_files.create(CreateAssistantFileRequest {
             file_id: oa_file.id.clone(),
-        })
-        .await?;
+        }).await?)
+    }).await?;
 
     // -- Asset waring.
     if oa_file.id != asst_file_obj.id {
-        println!(
+        eprintln!(
             "SHOULD NOT HAPPEN. File id not matching {} {}",
             oa_file.id, asst_file_obj.id
         );
@@ -328,13 +860,14 @@ pub async fn get_files_hashmap(
     // -- Get all asst files (files don't have .name)
     let oa_assts = oac.assistants();
     let oa_asst_files = oa_assts.files(asst_id);
-    let asst_files = oa_asst_files.list(DEFAULT_QUERY).await?.data;
+    let asst_files = oac.retry().run(|| async {
+        Ok(oa_asst_files.list(DEFAULT_QUERY).await?.data)
+    }).await?;
     let asst_file_ids: HashSet<String> = asst_files.into_iter().map(|f| f.id).collect();
 
     // -- Get all files for org (those files have .filename)
-    let oa_files = oac.files();
-    let org_files = oa_files.list(&[("purpose", "assistants")]).await?.data;
-    
+    let org_files = list_files(oac).await?;
+
     // -- Build or file_name:file_id hashmap
     let file_id_by_name: HashMap<String, FileId> = org_files
         .into_iter()
@@ -345,4 +878,68 @@ pub async fn get_files_hashmap(
     Ok(file_id_by_name)
 }
 
-// endregion: --- Files
\ No newline at end of file
+/// Returns the files of the account uploaded for the assistants.
+pub async fn list_files(oac: &OaClient) -> Result<Vec<OpenAIFile>> {
+    let oa_files = oac.files();
+    let org_files = oac.retry().run(|| async {
+        Ok(oa_files.list(&[("purpose", "assistants")]).await?.data)
+    }).await?;
+
+    Ok(org_files)
+}
+
+pub async fn delete_file(oac: &OaClient, file_id: &FileId) -> Result<()> {
+    let oa_files = oac.files();
+    oac.retry().run(|| async { Ok(oa_files.delete(file_id).await?) }).await?;
+
+    Ok(())
+}
+
+// endregion: --- Files
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Result<T> = core::result::Result<T, Error>; // For tests.
+
+    use super::*;
+    use crate::ais::new_oa_client;
+    use crate::ais::retry::RetryPolicy;
+    use crate::test_support::{tool_ctx, MockOaServer};
+
+    #[tokio::test]
+    async fn test_stream_interrupted_before_run_created_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let server = MockOaServer::start().await;
+        let oac = new_oa_client(Some(server.api_base()), RetryPolicy::default())?;
+        let tmp = tempfile::tempdir()?;
+        let tools = Tools::new(&[], tool_ctx(tmp.path()))?;
+        let thread_id = create_thred(&oac).await?;
+        create_user_msg(&oac, &thread_id, "Hello mock:stream_stalled").await?;
+        let run_request = CreateRunRequest {
+            assistant_id: "asst-mock".to_string(),
+            ..Default::default()
+        };
+        let res = post_stream(&oac, &format!("/threads/{thread_id}/runs"), run_request).await?;
+
+        // -- Exec
+        // Note: no event is streamed, so interrupted before `thread.run.created`.
+        let interrupt = sleep(Duration::from_millis(50));
+        let timeout = Duration::from_secs(10);
+        let (text, interrupted) =
+            stream_run_events(&oac, &thread_id, res, &tools, timeout, &mut |_| (), interrupt).await?;
+
+        // -- Check
+        assert!(interrupted);
+        assert!(text.is_empty());
+        let all_cancelled = server.state().runs.values().all(|run| run.obj["status"] == "cancelled");
+        assert!(all_cancelled, "the run should be cancelled");
+        create_user_msg(&oac, &thread_id, "Hello again").await?;
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
@@ -0,0 +1,219 @@
+// region:    --- Modules
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::path::Path;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::Result;
+use crate::ais::asst::{AsstId, CreateConfig, FileId, OnDelta, PollPolicy, ThreadId};
+use crate::ais::retry::RetryPolicy;
+use crate::tools::Tools;
+
+pub use self::oa_assts::OaAsstsBackend;
+pub use self::oa_chat::OaChatBackend;
+
+mod oa_assts;
+mod oa_chat;
+
+// endregion: --- Modules
+
+/// Separates the user text of a message from the appended search hits.
+pub const SEARCH_HITS_MARKER: &str = "\n\n---\nRelevant excerpts of the project files:\n";
+
+// region:    --- Types
+
+/// The AI provider, selected by the `backend` key of `rusty_ai.toml`.
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum BackendKind {
+    /// OpenAI Assistants API (assistants, files, threads and runs).
+    #[default]
+    OpenaiAssistants,
+    /// Chat Completions API (any compatible endpoint), with the conversations
+    /// and the files retrieval done locally.
+    OpenaiChat,
+}
+
+/// Assistant summary (for the `assistants` commands).
+#[derive(Debug)]
+pub struct AsstInfo {
+    pub id: AsstId,
+    pub name: Option<String>,
+    pub model: String,
+    /// Unix timestamp (seconds)
+    pub created_at: i64,
+    /// Names of the attached files (sorted)
+    pub file_names: Vec<String>,
+}
+
+/// Uploaded file summary (for the `gc` command).
+#[derive(Debug)]
+pub struct FileInfo {
+    pub id: FileId,
+    pub name: String,
+    pub bytes: u64,
+    /// Unix timestamp (seconds)
+    pub created_at: i64,
+}
+
+/// A message of a thread (for the history and export).
+#[derive(Debug, Serialize)]
+pub struct ThreadMsg {
+    /// "user" or "assistant"
+    pub role: String,
+    pub content: String,
+    /// Unix timestamp (seconds)
+    pub created_at: i64,
+}
+
+/// The user message of a run.
+#[derive(Debug, Clone, Copy)]
+pub struct RunMsg<'a> {
+    /// The user text, possibly followed by search hits (see `SEARCH_HITS_MARKER`)
+    pub content: &'a str,
+    /// Retry after a failed run, the message might already be in the thread.
+    pub retry: bool,
+}
+
+impl RunMsg<'_> {
+    /// True if the thread message `content` is this message, even with other
+    /// search hits (e.g., the index updated since the failed run).
+    pub fn is_same_msg(&self, content: &str) -> bool {
+        user_text(content) == user_text(self.content)
+    }
+}
+
+/// Returns the user text of a message `content`, without its search hits.
+pub fn user_text(content: &str) -> &str {
+    content.find(SEARCH_HITS_MARKER).map_or(content, |idx| &content[..idx])
+}
+
+/// What is needed to create a backend.
+pub struct BackendConfig {
+    pub kind: BackendKind,
+    /// Custom OpenAI compatible api base (e.g., `http://localhost:8080/v1`)
+    pub api_base: Option<String>,
+    pub retry: RetryPolicy,
+    /// Polling of the runs (assistants backend)
+    pub poll: PollPolicy,
+}
+
+// endregion: --- Types
+
+// region:    --- Backend
+
+/// The operations `RustyAI` needs from an AI provider.
+#[async_trait]
+pub trait AiBackend: Debug + Send + Sync {
+    /// Loads the newest assistant of the `config` name, or creates it.
+    /// - `recreate` creates a new one, the previous ones being left to the
+    ///   caller (deleted once the new one is ready).
+    async fn load_or_create_asst(
+        &self,
+        config: CreateConfig,
+        recreate: bool,
+    ) -> Result<AsstId>;
+
+    /// Returns all the assistants (newest first).
+    async fn list_assts(&self) -> Result<Vec<AsstInfo>>;
+
+    /// Deletes the assistant and its uploaded files.
+    async fn delete_asst(&self, asst_id: &AsstId) -> Result<()>;
+
+    async fn upload_instructions(
+        &self,
+        asst_id: &AsstId,
+        inst_content: String,
+    ) -> Result<()>;
+
+    /// Returns `(FileId, has_been_uploaded)`
+    async fn upload_file_by_name(
+        &self,
+        asst_id: &AsstId,
+        file: &Path,
+        force: bool,
+    ) -> Result<(FileId, bool)>;
+
+    /// Returns the file id by file name hashmap.
+    async fn get_files_hashmap(
+        &self,
+        asst_id: &AsstId,
+    ) -> Result<HashMap<String, FileId>>;
+
+    /// Returns all the files uploaded to the provider (attached or not).
+    async fn list_files(&self) -> Result<Vec<FileInfo>>;
+
+    async fn delete_file(&self, file_id: &FileId) -> Result<()>;
+
+    async fn create_thread(&self) -> Result<ThreadId>;
+
+    /// Returns an error if the thread does not exist (anymore).
+    async fn get_thread(&self, thread_id: &ThreadId) -> Result<()>;
+
+    async fn delete_thread(&self, thread_id: &ThreadId) -> Result<()>;
+
+    /// Cancels the runs still active on the thread (none by default).
+    ///
+    /// Returns the number of cancelled runs.
+    async fn cancel_active_runs(&self, _thread_id: &ThreadId) -> Result<usize> {
+        Ok(0)
+    }
+
+    /// Returns all the messages of the thread (oldest first).
+    async fn list_thread_msgs(&self, thread_id: &ThreadId) -> Result<Vec<ThreadMsg>>;
+
+    /// Returns an `Error::RunTimeout` when not done after `timeout`.
+    /// The function calls of the run are answered with the local `tools`
+    /// (ignored by the backends without function tools).
+    async fn run_thread_msg(
+        &self,
+        asst_id: &AsstId,
+        thread_id: &ThreadId,
+        msg: RunMsg<'_>,
+        tools: &Tools,
+        timeout: Duration,
+    ) -> Result<String>;
+
+    /// Same as `run_thread_msg`, but calls `on_delta` with the text as it arrives.
+    /// Backends that cannot stream fall back to `run_thread_msg` (one delta).
+    ///
+    /// Returns `(response_text, has_been_interrupted)`
+    async fn run_thread_msg_stream(
+        &self,
+        asst_id: &AsstId,
+        thread_id: &ThreadId,
+        msg: RunMsg<'_>,
+        tools: &Tools,
+        timeout: Duration,
+        on_delta: &mut OnDelta<'_>,
+    ) -> Result<(String, bool)> {
+        let res = self.run_thread_msg(asst_id, thread_id, msg, tools, timeout).await?;
+        on_delta(&res);
+        Ok((res, false))
+    }
+}
+
+/// Creates the backend, `data_dir` being the `.rusty_ai` dir for the backends
+/// storing data locally.
+pub fn new_backend(
+    config: BackendConfig,
+    data_dir: &Path,
+) -> Result<Box<dyn AiBackend>> {
+    let api_base = config.api_base.as_deref();
+    let retry = config.retry;
+
+    let backend: Box<dyn AiBackend> = match config.kind {
+        BackendKind::OpenaiAssistants => {
+            Box::new(OaAsstsBackend::new(api_base, retry, config.poll)?)
+        }
+        BackendKind::OpenaiChat => Box::new(OaChatBackend::new(api_base, retry, data_dir)?),
+    };
+
+    Ok(backend)
+}
+
+// endregion: --- Backend
@@ -0,0 +1,168 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+use async_openai::types::MessageRole;
+use async_trait::async_trait;
+
+use crate::Result;
+use crate::ais::{new_oa_client, OaClient};
+use crate::ais::asst::{self, AsstId, CreateConfig, FileId, OnDelta, PollPolicy, ThreadId};
+use crate::ais::msg::get_text_content;
+use crate::ais::retry::RetryPolicy;
+use crate::tools::Tools;
+use crate::ais::backend::{AiBackend, AsstInfo, FileInfo, RunMsg, ThreadMsg};
+
+/// The OpenAI Assistants API backend (delegates to `ais::asst`).
+#[derive(Debug)]
+pub struct OaAsstsBackend {
+    oac: OaClient,
+    poll: PollPolicy,
+}
+
+impl OaAsstsBackend {
+    pub fn new(api_base: Option<&str>, retry: RetryPolicy, poll: PollPolicy) -> Result<Self> {
+        Ok(Self {
+            oac: new_oa_client(api_base, retry)?,
+            poll,
+        })
+    }
+}
+
+#[async_trait]
+impl AiBackend for OaAsstsBackend {
+    async fn load_or_create_asst(
+        &self,
+        config: CreateConfig,
+        recreate: bool,
+    ) -> Result<AsstId> {
+        asst::load_or_create_asst(&self.oac, config, recreate).await
+    }
+
+    async fn list_assts(&self) -> Result<Vec<AsstInfo>> {
+        let mut infos = Vec::new();
+        for asst_obj in asst::list(&self.oac).await? {
+            let id = AsstId::from(asst_obj.id);
+            let mut file_names: Vec<String> = asst::get_files_hashmap(&self.oac, &id)
+                .await?
+                .into_keys()
+                .collect();
+            file_names.sort();
+
+            infos.push(AsstInfo {
+                id,
+                name: asst_obj.name,
+                model: asst_obj.model,
+                created_at: asst_obj.created_at as i64,
+                file_names,
+            });
+        }
+
+        Ok(infos)
+    }
+
+    async fn delete_asst(&self, asst_id: &AsstId) -> Result<()> {
+        asst::delete(&self.oac, asst_id).await
+    }
+
+    async fn upload_instructions(
+        &self,
+        asst_id: &AsstId,
+        inst_content: String,
+    ) -> Result<()> {
+        asst::upload_instructions(&self.oac, asst_id, inst_content).await
+    }
+
+    async fn upload_file_by_name(
+        &self,
+        asst_id: &AsstId,
+        file: &Path,
+        force: bool,
+    ) -> Result<(FileId, bool)> {
+        asst::upload_file_by_name(&self.oac, asst_id, file, force).await
+    }
+
+    async fn get_files_hashmap(
+        &self,
+        asst_id: &AsstId,
+    ) -> Result<HashMap<String, FileId>> {
+        asst::get_files_hashmap(&self.oac, asst_id).await
+    }
+
+    async fn list_files(&self) -> Result<Vec<FileInfo>> {
+        let files = asst::list_files(&self.oac)
+            .await?
+            .into_iter()
+            .map(|f| FileInfo {
+                id: FileId::from(f.id),
+                name: f.filename,
+                bytes: f.bytes as u64,
+                created_at: f.created_at as i64,
+            })
+            .collect();
+
+        Ok(files)
+    }
+
+    async fn delete_file(&self, file_id: &FileId) -> Result<()> {
+        asst::delete_file(&self.oac, file_id).await
+    }
+
+    async fn create_thread(&self) -> Result<ThreadId> {
+        asst::create_thred(&self.oac).await
+    }
+
+    async fn get_thread(&self, thread_id: &ThreadId) -> Result<()> {
+        asst::get_thread(&self.oac, thread_id).await?;
+        Ok(())
+    }
+
+    async fn delete_thread(&self, thread_id: &ThreadId) -> Result<()> {
+        asst::delete_thread(&self.oac, thread_id).await
+    }
+
+    async fn cancel_active_runs(&self, thread_id: &ThreadId) -> Result<usize> {
+        asst::cancel_active_runs(&self.oac, thread_id).await
+    }
+
+    async fn list_thread_msgs(&self, thread_id: &ThreadId) -> Result<Vec<ThreadMsg>> {
+        let mut msgs = Vec::new();
+        for msg_obj in asst::list_thread_msgs(&self.oac, thread_id).await? {
+            let role = match msg_obj.role {
+                MessageRole::User => "user",
+                MessageRole::Assistant => "assistant",
+            };
+            msgs.push(ThreadMsg {
+                role: role.to_string(),
+                created_at: msg_obj.created_at as i64,
+                content: get_text_content(msg_obj)?,
+            });
+        }
+
+        Ok(msgs)
+    }
+
+    async fn run_thread_msg(
+        &self,
+        asst_id: &AsstId,
+        thread_id: &ThreadId,
+        msg: RunMsg<'_>,
+        tools: &Tools,
+        timeout: Duration,
+    ) -> Result<String> {
+        asst::run_thread_msg(&self.oac, asst_id, thread_id, msg, tools, &self.poll, timeout).await
+    }
+
+    async fn run_thread_msg_stream(
+        &self,
+        asst_id: &AsstId,
+        thread_id: &ThreadId,
+        msg: RunMsg<'_>,
+        tools: &Tools,
+        timeout: Duration,
+        on_delta: &mut OnDelta<'_>,
+    ) -> Result<(String, bool)> {
+        asst::run_thread_msg_stream(&self.oac, asst_id, thread_id, msg, tools, timeout, on_delta)
+            .await
+    }
+}
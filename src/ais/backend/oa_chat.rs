@@ -0,0 +1,551 @@
+//! Chat Completions backend.
+//!
+//! Everything the Assistants API keeps server-side is kept locally under
+//! `.rusty_ai/chat/` (assistant, attached files, threads), and the relevant
+//! chunks of the attached bundle files are selected locally and sent with
+//! each chat completion request.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_openai::types::{
+    ChatCompletionRequestAssistantMessage,
+    ChatCompletionRequestMessage,
+    ChatCompletionRequestSystemMessage,
+    ChatCompletionRequestUserMessage,
+    CreateChatCompletionRequest,
+    Role,
+};
+use async_trait::async_trait;
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
+
+use crate::{Error, FileOp, Result};
+use crate::ais::{new_oa_client, OaClient};
+use crate::ais::asst::{AsstId, CreateConfig, FileId, OnDelta, ThreadId};
+use crate::ais::backend::{AiBackend, AsstInfo, FileInfo, RunMsg, ThreadMsg};
+use crate::ais::retry::RetryPolicy;
+use crate::tools::Tools;
+use crate::retrieval::{top_chunks, Index};
+use crate::utils::cli::{ico_check, ico_uploaded};
+use crate::utils::files::{ensure_dir, file_io, list_files, load_from_json, save_to_json, XFile};
+
+// region:    --- Constants
+
+/// Max number of history messages sent with each request.
+const HISTORY_MAX_MSGS: usize = 20;
+/// Max chars of bundle chunks sent with each request.
+const CONTEXT_MAX_CHARS: usize = 12_000;
+
+// endregion: --- Constants
+
+// region:    --- Types
+
+#[derive(Debug)]
+pub struct OaChatBackend {
+    oac: OaClient,
+    /// The `.rusty_ai/chat` dir
+    dir: PathBuf,
+    /// The index of the bundle files of the last request
+    index: Mutex<Option<CachedIndex>>,
+}
+
+#[derive(Debug)]
+struct CachedIndex {
+    /// The indexed bundle files, with their (modified time, len) at build
+    files: Vec<(PathBuf, Option<(SystemTime, u64)>)>,
+    index: Arc<Index>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LocalAsst {
+    name: String,
+    model: String,
+    instructions: Option<String>,
+    #[serde(default)]
+    created_at: u64,
+    /// file name -> bundle file path
+    files: HashMap<String, PathBuf>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct LocalThread {
+    msgs: Vec<LocalMsg>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LocalMsg {
+    role: String,
+    content: String,
+    created_at: u64,
+}
+
+// endregion: --- Types
+
+// region:    --- Constructor & Store
+
+impl OaChatBackend {
+    pub fn new(api_base: Option<&str>, retry: RetryPolicy, data_dir: &Path) -> Result<Self> {
+        let dir = data_dir.join("chat");
+        ensure_dir(&dir.join("threads"))?;
+
+        Ok(Self {
+            oac: new_oa_client(api_base, retry)?,
+            dir,
+            index: Mutex::new(None),
+        })
+    }
+
+    fn asst_file(&self, asst_id: &AsstId) -> PathBuf {
+        self.dir.join(format!("{asst_id}.json"))
+    }
+
+    fn thread_file(&self, thread_id: &ThreadId) -> PathBuf {
+        self.dir.join("threads").join(format!("{thread_id}.json"))
+    }
+
+    /// Returns all the local assistants (an unreadable one is an error, so it
+    /// is never silently replaced).
+    fn load_assts(&self) -> Result<Vec<(AsstId, LocalAsst)>> {
+        let mut assts = Vec::new();
+        for file in list_files(&self.dir, Some(&["*.json"]), None)? {
+            let asst: LocalAsst = match load_from_json(&file) {
+                Ok(asst) => asst,
+                // e.g., deleted since listed
+                Err(Error::FileNotFound(_)) => continue,
+                Err(err) => return Err(err),
+            };
+            assts.push((AsstId::from(file.x_file_stem().to_string()), asst));
+        }
+
+        Ok(assts)
+    }
+
+    fn load_asst(&self, asst_id: &AsstId) -> Result<LocalAsst> {
+        load_from_json(self.asst_file(asst_id))
+            .map_err(|_| Error::AsstNotFound { name_or_id: asst_id.to_string() })
+    }
+}
+
+fn new_local_id(prefix: &str) -> Result<String> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)?;
+    Ok(format!("{prefix}-{}", now.as_micros()))
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// endregion: --- Constructor & Store
+
+// region:    --- Backend
+
+#[async_trait]
+impl AiBackend for OaChatBackend {
+    async fn load_or_create_asst(
+        &self,
+        config: CreateConfig,
+        recreate: bool,
+    ) -> Result<AsstId> {
+        // -- Load the newest of the name (model always from config)
+        if !recreate {
+            let newest = self
+                .load_assts()?
+                .into_iter()
+                .filter(|(_, asst)| asst.name == config.name)
+                .max_by(|(id_a, a), (id_b, b)| {
+                    (a.created_at, id_a.as_str()).cmp(&(b.created_at, id_b.as_str()))
+                });
+            if let Some((asst_id, asst)) = newest {
+                save_to_json(self.asst_file(&asst_id), &LocalAsst { model: config.model, ..asst })?;
+                eprintln!("{} Assistant {} loaded", ico_check(), config.name);
+                return Ok(asst_id);
+            }
+        }
+
+        // -- Create (when recreate, a new one, the previous ones being
+        //    deleted by the caller once it is ready)
+        let asst_id = if recreate {
+            AsstId::from(new_local_id(&format!("asst-{}", config.name))?)
+        } else {
+            AsstId::from(format!("asst-{}", config.name))
+        };
+        let asst = LocalAsst {
+            name: config.name,
+            model: config.model,
+            instructions: None,
+            created_at: now_secs(),
+            files: HashMap::new(),
+        };
+        save_to_json(self.asst_file(&asst_id), &asst)?;
+        eprintln!("{} Assistant {} created", ico_check(), asst.name);
+
+        Ok(asst_id)
+    }
+
+    async fn list_assts(&self) -> Result<Vec<AsstInfo>> {
+        let mut infos = Vec::new();
+        for (asst_id, asst) in self.load_assts()? {
+            let mut file_names: Vec<String> = asst.files.into_keys().collect();
+            file_names.sort();
+
+            infos.push(AsstInfo {
+                id: asst_id,
+                name: Some(asst.name),
+                model: asst.model,
+                created_at: asst.created_at as i64,
+                file_names,
+            });
+        }
+        infos.sort_by_key(|a| Reverse(a.created_at));
+
+        Ok(infos)
+    }
+
+    /// Only the local assistant is deleted (the bundle files stay).
+    async fn delete_asst(&self, asst_id: &AsstId) -> Result<()> {
+        fs::remove_file(self.asst_file(asst_id))
+            .map_err(|_| Error::AsstNotFound { name_or_id: asst_id.to_string() })?;
+        Ok(())
+    }
+
+    async fn upload_instructions(
+        &self,
+        asst_id: &AsstId,
+        inst_content: String,
+    ) -> Result<()> {
+        let mut asst = self.load_asst(asst_id)?;
+        asst.instructions = Some(inst_content);
+        save_to_json(self.asst_file(asst_id), &asst)?;
+
+        Ok(())
+    }
+
+    /// The file is not uploaded, its path is attached to the local assistant
+    /// (its content is read at each request).
+    async fn upload_file_by_name(
+        &self,
+        asst_id: &AsstId,
+        file: &Path,
+        force: bool,
+    ) -> Result<(FileId, bool)> {
+        let file_name = file.x_file_name().to_string();
+        let file_id = FileId::from(format!("file-{file_name}"));
+
+        let mut asst = self.load_asst(asst_id)?;
+        if !force && asst.files.contains_key(&file_name) {
+            return Ok((file_id, false));
+        }
+
+        asst.files.insert(file_name.clone(), file.to_path_buf());
+        save_to_json(self.asst_file(asst_id), &asst)?;
+        eprintln!("{} Attached file '{}'", ico_uploaded(), file_name);
+
+        Ok((file_id, true))
+    }
+
+    async fn get_files_hashmap(
+        &self,
+        asst_id: &AsstId,
+    ) -> Result<HashMap<String, FileId>> {
+        let asst = self.load_asst(asst_id)?;
+
+        Ok(asst
+            .files
+            .into_keys()
+            .map(|name| {
+                let file_id = FileId::from(format!("file-{name}"));
+                (name, file_id)
+            })
+            .collect())
+    }
+
+    /// Nothing is uploaded (the bundle files are read locally).
+    async fn list_files(&self) -> Result<Vec<FileInfo>> {
+        Ok(Vec::new())
+    }
+
+    async fn delete_file(&self, file_id: &FileId) -> Result<()> {
+        Err(Error::NoUploadedFile { file_id: file_id.to_string() })
+    }
+
+    async fn create_thread(&self) -> Result<ThreadId> {
+        let thread_id = ThreadId::from(new_local_id("thread")?);
+        save_to_json(self.thread_file(&thread_id), &LocalThread::default())?;
+
+        Ok(thread_id)
+    }
+
+    async fn get_thread(&self, thread_id: &ThreadId) -> Result<()> {
+        let thread_file = self.thread_file(thread_id);
+        if !thread_file.is_file() {
+            return Err(Error::ThreadNotFound { thread_id: thread_id.to_string() });
+        }
+        load_from_json::<LocalThread>(thread_file)?;
+        Ok(())
+    }
+
+    async fn delete_thread(&self, thread_id: &ThreadId) -> Result<()> {
+        let thread_file = self.thread_file(thread_id);
+        fs::remove_file(&thread_file).map_err(file_io(FileOp::Delete, &thread_file))?;
+        Ok(())
+    }
+
+    async fn list_thread_msgs(&self, thread_id: &ThreadId) -> Result<Vec<ThreadMsg>> {
+        let thread: LocalThread = load_from_json(self.thread_file(thread_id))?;
+
+        Ok(thread
+            .msgs
+            .into_iter()
+            .map(|m| ThreadMsg {
+                role: m.role,
+                content: m.content,
+                created_at: m.created_at as i64,
+            })
+            .collect())
+    }
+
+    async fn run_thread_msg(
+        &self,
+        asst_id: &AsstId,
+        thread_id: &ThreadId,
+        msg: RunMsg<'_>,
+        _tools: &Tools,
+        timeout: Duration,
+    ) -> Result<String> {
+        // Note: no function calling with this backend, `tools` ignored (warned at init).
+        // Note: the failed exchanges are not saved, so the retries are as the first run.
+        let msg = msg.content;
+        let (mut thread, request) = self.prep_request(asst_id, thread_id, msg)?;
+
+        let res = self.oac.retry().run_once(|| async {
+            Ok(self.oac.chat().create(request.clone()).await?)
+        });
+        let res = tokio::select! {
+            res = tokio::time::timeout(timeout, res) => {
+                res.map_err(|_| Error::RunTimeout { timeout })??
+            }
+            _ = tokio::signal::ctrl_c() => return Err(Error::Interrupted),
+        };
+        let answer = res
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|c| c.message.content)
+            .ok_or(Error::NoMessage)?;
+
+        self.save_exchange(thread_id, &mut thread, msg, &answer)?;
+
+        Ok(answer)
+    }
+
+    async fn run_thread_msg_stream(
+        &self,
+        asst_id: &AsstId,
+        thread_id: &ThreadId,
+        msg: RunMsg<'_>,
+        _tools: &Tools,
+        timeout: Duration,
+        on_delta: &mut OnDelta<'_>,
+    ) -> Result<(String, bool)> {
+        // Note: the failed exchanges are not saved, so the retries are as the first run.
+        let msg = msg.content;
+        let (mut thread, request) = self.prep_request(asst_id, thread_id, msg)?;
+        let out_of_time = sleep(timeout);
+        tokio::pin!(out_of_time);
+
+        let mut stream = self.oac.retry().run_once(|| async {
+            Ok(self.oac.chat().create_stream(request.clone()).await?)
+        }).await?;
+        let mut answer = String::new();
+        let mut interrupted = false;
+
+        let ctrl_c = tokio::signal::ctrl_c();
+        tokio::pin!(ctrl_c);
+
+        loop {
+            let res = tokio::select! {
+                res = stream.next() => res,
+                _ = &mut ctrl_c => {
+                    interrupted = true;
+                    break;
+                }
+                _ = &mut out_of_time => return Err(Error::RunTimeout { timeout }),
+            };
+            let Some(res) = res else {
+                break;
+            };
+
+            for choice in res?.choices {
+                if let Some(delta) = choice.delta.content {
+                    on_delta(&delta);
+                    answer.push_str(&delta);
+                }
+            }
+        }
+
+        self.save_exchange(thread_id, &mut thread, msg, &answer)?;
+
+        Ok((answer, interrupted))
+    }
+}
+
+// endregion: --- Backend
+
+// region:    --- Request
+
+impl OaChatBackend {
+    /// Returns the index of the `bundle_files`, only rebuilt when they changed
+    /// (e.g., re-uploaded).
+    fn bundles_index(&self, bundle_files: &[&PathBuf]) -> Result<Arc<Index>> {
+        let mut files: Vec<(PathBuf, Option<(SystemTime, u64)>)> = bundle_files
+            .iter()
+            .map(|file| {
+                let stamp = file.metadata().and_then(|m| Ok((m.modified()?, m.len()))).ok();
+                (file.to_path_buf(), stamp)
+            })
+            .collect();
+        files.sort();
+
+        let mut cached = self.index.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(cached) = cached.as_ref().filter(|c| c.files == files) {
+            return Ok(cached.index.clone());
+        }
+        let index = Arc::new(Index::build(bundle_files)?);
+        *cached = Some(CachedIndex { files, index: index.clone() });
+
+        Ok(index)
+    }
+
+    /// Returns the thread and the request with: the system message
+    /// (instructions and relevant bundle chunks), the thread history, and `msg`.
+    fn prep_request(
+        &self,
+        asst_id: &AsstId,
+        thread_id: &ThreadId,
+        msg: &str,
+    ) -> Result<(LocalThread, CreateChatCompletionRequest)> {
+        let asst = self.load_asst(asst_id)?;
+        let thread: LocalThread = load_from_json(self.thread_file(thread_id))?;
+
+        // -- System message
+        let mut system = asst.instructions.clone().unwrap_or_default();
+        let bundle_files: Vec<&PathBuf> =
+            asst.files.values().filter(|f| f.is_file()).collect();
+        let index = self.bundles_index(&bundle_files)?;
+        let chunks = top_chunks(&index, msg, CONTEXT_MAX_CHARS);
+        if !chunks.is_empty() {
+            system.push_str("\n\nRelevant excerpts of the files:\n");
+            for chunk in chunks {
+                system.push_str(&format!(
+                    "\n==== file path: {} (from line {})\n{}\n",
+                    chunk.path, chunk.start_line, chunk.content
+                ));
+            }
+        }
+
+        let mut messages: Vec<ChatCompletionRequestMessage> =
+            vec![ChatCompletionRequestSystemMessage {
+                content: system,
+                role: Role::System,
+                ..Default::default()
+            }
+            .into()];
+
+        // -- History & user message
+        let history_start = thread.msgs.len().saturating_sub(HISTORY_MAX_MSGS);
+        for local_msg in &thread.msgs[history_start..] {
+            messages.push(to_request_msg(&local_msg.role, &local_msg.content));
+        }
+        messages.push(to_request_msg("user", msg));
+
+        let request = CreateChatCompletionRequest {
+            model: asst.model,
+            messages,
+            ..Default::default()
+        };
+
+        Ok((thread, request))
+    }
+
+    fn save_exchange(
+        &self,
+        thread_id: &ThreadId,
+        thread: &mut LocalThread,
+        msg: &str,
+        answer: &str,
+    ) -> Result<()> {
+        let created_at = now_secs();
+        for (role, content) in [("user", msg), ("assistant", answer)] {
+            thread.msgs.push(LocalMsg {
+                role: role.to_string(),
+                content: content.to_string(),
+                created_at,
+            });
+        }
+
+        save_to_json(self.thread_file(thread_id), thread)
+    }
+}
+
+fn to_request_msg(role: &str, content: &str) -> ChatCompletionRequestMessage {
+    if role == "assistant" {
+        ChatCompletionRequestAssistantMessage {
+            content: Some(content.to_string()),
+            role: Role::Assistant,
+            ..Default::default()
+        }
+        .into()
+    } else {
+        ChatCompletionRequestUserMessage {
+            content: content.into(),
+            role: Role::User,
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+// endregion: --- Request
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Result<T> = core::result::Result<T, Error>; // For tests.
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_bundles_index_cached_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let dir = tempfile::tempdir()?;
+        let backend = OaChatBackend::new(Some("http://localhost:1"), RetryPolicy::default(), dir.path())?;
+        let bundle_file = dir.path().join("bundle.txt");
+        fs::write(&bundle_file, "// ==== file path: src/main.rs\n\nfn main() {}\n")?;
+
+        // -- Exec
+        let first = backend.bundles_index(&[&bundle_file])?;
+        let same = backend.bundles_index(&[&bundle_file])?;
+        fs::write(&bundle_file, "// ==== file path: src/main.rs\n\nfn main() {}\n\nfn run() {}\n")?;
+        let rebuilt = backend.bundles_index(&[&bundle_file])?;
+
+        // -- Check
+        assert!(Arc::ptr_eq(&first, &same), "index should be reused");
+        assert!(!Arc::ptr_eq(&first, &rebuilt), "index should be rebuilt");
+        assert_eq!(rebuilt.num_chunks(), 2);
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
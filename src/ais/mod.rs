@@ -1,11 +1,19 @@
 // region:    --- Modules
 
+use std::time::Duration;
+
 use async_openai::{Client, config::OpenAIConfig};
+use backoff::ExponentialBackoff;
+use derive_more::Deref;
 
-use crate::Result;
+use crate::{Error, Result};
+use self::retry::RetryPolicy;
 
 pub mod asst;
+pub mod backend;
 pub mod msg;
+pub mod retry;
+mod sse;
 
 // endregion: --- Modules
 
@@ -13,16 +21,52 @@ pub mod msg;
 
 const ENV_OPENAI_API_KEY: &str = "OPENAI_API_KEY";
 
-pub type OaClient = Client<OpenAIConfig>;
+/// The OpenAI client, with the retry policy of its requests.
+#[derive(Debug, Clone, Deref)]
+pub struct OaClient {
+    #[deref]
+    client: Client<OpenAIConfig>,
+    /// The HTTP client of `client`, for the raw requests (e.g., streamed runs)
+    http: reqwest::Client,
+    retry: RetryPolicy,
+}
 
-pub fn new_oa_client() -> Result<OaClient> {
-    if std::env::var(ENV_OPENAI_API_KEY).is_ok() {
-        Ok(Client::new())
-    } else {
-        println!("No {ENV_OPENAI_API_KEY} env variable. Please set it.");
-        
-        Err("No openai api key in env".into())
+impl OaClient {
+    pub fn retry(&self) -> &RetryPolicy {
+        &self.retry
     }
+
+    pub fn http(&self) -> &reqwest::Client {
+        &self.http
+    }
+}
+
+/// Creates the OpenAI client.
+/// - `api_base` is `None`, targets the OpenAI API and requires the api key env.
+/// - `api_base` is `Some`, targets this OpenAI compatible endpoint
+///   (api key env optional, e.g., local server).
+pub fn new_oa_client(api_base: Option<&str>, retry: RetryPolicy) -> Result<OaClient> {
+    let client = if let Some(api_base) = api_base {
+        let config = OpenAIConfig::new().with_api_base(api_base);
+        Client::with_config(config)
+    } else if std::env::var(ENV_OPENAI_API_KEY).is_ok() {
+        Client::new()
+    } else {
+        return Err(Error::MissingApiKey { env_name: ENV_OPENAI_API_KEY });
+    };
+
+    // Note: Disables the async-openai silent rate limit retries (up to 15 minutes),
+    //       the `RetryPolicy` does them.
+    let client = client.with_backoff(ExponentialBackoff {
+        max_elapsed_time: Some(Duration::ZERO),
+        ..Default::default()
+    });
+
+    // Note: shared with the raw requests (one connection pool and proxy setup).
+    let http = reqwest::Client::new();
+    let client = client.with_http_client(http.clone());
+
+    Ok(OaClient { client, http, retry })
 }
 
 // endregion: --- Client
\ No newline at end of file
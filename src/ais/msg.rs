@@ -1,6 +1,7 @@
 use async_openai::types::{CreateMessageRequest, MessageObject, MessageContent};
+use serde_json::Value;
 
-use crate::Result;
+use crate::{Error, Result};
 
 // region:    --- Message Constructors
 
@@ -23,17 +24,30 @@ pub fn get_text_content(msg: MessageObject) -> Result<String> {
         .content
         .into_iter()
         .next()
-        .ok_or_else(|| "No msg content found".to_string())?;
+        .ok_or(Error::NoMessage)?;
 
     // -- Get the text
     let txt = match msg_content {
         MessageContent::Text(text) => text.text.value,
         MessageContent::ImageFile(_) => {
-            return Err("Message image not supported yet".into());
+            return Err(Error::MsgContentNotText);
         }
     };
 
     Ok(txt)
 }
 
+/// Returns the text of a streamed `thread.message.delta` event data
+/// (None if the delta has no text).
+pub fn get_text_delta(delta_data: &Value) -> Option<String> {
+    let contents = delta_data["delta"]["content"].as_array()?;
+
+    let txt: String = contents
+        .iter()
+        .filter_map(|c| c["text"]["value"].as_str())
+        .collect();
+
+    (!txt.is_empty()).then_some(txt)
+}
+
 // region:    --- Content Extractor
\ No newline at end of file
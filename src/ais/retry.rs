@@ -0,0 +1,225 @@
+//! Retry policy of the OpenAI requests (jittered exponential backoff).
+
+use std::future::Future;
+use std::time::Duration;
+
+use rand::Rng;
+use reqwest::header::HeaderMap;
+use serde::Deserialize;
+use tokio::time::sleep;
+
+use crate::{Error, Result};
+use crate::utils::cli::ico_retry;
+
+// region:    --- Types
+
+/// The `[retry]` table of `rusty_ai.toml`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RetryPolicy {
+    /// Max retries after the first attempt (0 to disable)
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled on each next retry
+    pub base_delay_ms: u64,
+    /// Cap of the backoff delay (the retry-after hints of the API are not capped)
+    pub max_delay_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 4,
+            base_delay_ms: 500,
+            max_delay_ms: 20_000,
+        }
+    }
+}
+
+// endregion: --- Types
+
+// region:    --- Retry
+
+impl RetryPolicy {
+    /// Runs an idempotent request (e.g., list, retrieve, delete), retrying on
+    /// rate limit, server and network errors.
+    pub async fn run<T, F, Fut>(&self, op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.exec(op, true).await
+    }
+
+    /// Runs a non idempotent request (e.g., create), only retrying on rate limit
+    /// (the request was not processed).
+    pub async fn run_once<T, F, Fut>(&self, op: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        self.exec(op, false).await
+    }
+
+    async fn exec<T, F, Fut>(&self, mut op: F, idempotent: bool) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut retries = 0;
+
+        loop {
+            let err = match op().await {
+                Ok(res) => return Ok(res),
+                Err(err) => err,
+            };
+
+            let retry_after = match &err {
+                Error::RateLimited { retry_after, .. } => *retry_after,
+                err if idempotent && err.is_transient() => None,
+                _ => return Err(err),
+            };
+
+            if retries >= self.max_retries {
+                return Err(if retries == 0 {
+                    err
+                } else {
+                    Error::RetriesExhausted { retries, cause: Box::new(err) }
+                });
+            }
+
+            retries += 1;
+            let delay = self.delay(retries, retry_after);
+            eprintln!(
+                "{} {err} - retry {retries}/{} in {:.1}s",
+                ico_retry(),
+                self.max_retries,
+                delay.as_secs_f32()
+            );
+            sleep(delay).await;
+        }
+    }
+
+    /// Returns the delay before the retry number `retry` (starting at 1).
+    /// - The retry-after hint of the API if any.
+    /// - Otherwise `base_delay_ms * 2^(retry - 1)` capped to `max_delay_ms`,
+    ///   with a random jitter (50% to 100% of it).
+    fn delay(&self, retry: u32, retry_after: Option<Duration>) -> Duration {
+        if let Some(retry_after) = retry_after {
+            return retry_after;
+        }
+
+        let exp = 1u64 << (retry.saturating_sub(1)).min(16);
+        let delay_ms = self.base_delay_ms.saturating_mul(exp).min(self.max_delay_ms);
+        let delay_ms = rand::thread_rng().gen_range(delay_ms / 2..=delay_ms);
+
+        Duration::from_millis(delay_ms)
+    }
+}
+
+// endregion: --- Retry
+
+// region:    --- Retry After
+
+/// Returns the hint of the OpenAI rate limit error messages,
+/// e.g., "Please try again in 1.5s.", "Please try again in 20ms." or "Please try again in 1h2m10s."
+pub fn retry_after_from_msg(msg: &str) -> Option<Duration> {
+    let mut hint = msg.split("try again in ").nth(1)?;
+    let mut secs = None;
+
+    // Sums all the components (e.g., `2m10s` is 130s).
+    loop {
+        if !hint.starts_with(|c: char| c.is_ascii_digit()) {
+            break;
+        }
+        let num_end = hint.find(|c: char| !(c.is_ascii_digit() || c == '.')).unwrap_or(hint.len());
+        let (num, rest) = hint.split_at(num_end);
+        let num: f64 = num.parse().ok()?;
+
+        let (unit_secs, unit_len) = if rest.starts_with("ms") {
+            (0.001, 2)
+        } else if rest.starts_with('s') {
+            (1., 1)
+        } else if rest.starts_with('m') {
+            (60., 1)
+        } else if rest.starts_with('h') {
+            (3600., 1)
+        } else {
+            return None;
+        };
+        secs = Some(secs.unwrap_or(0.) + num * unit_secs);
+        hint = &rest[unit_len..];
+    }
+
+    secs.map(Duration::from_secs_f64)
+}
+
+/// Returns the `retry-after-ms` or `retry-after` (seconds) header value.
+pub fn retry_after_from_headers(headers: &HeaderMap) -> Option<Duration> {
+    let header = |name: &str| -> Option<f64> { headers.get(name)?.to_str().ok()?.parse().ok() };
+
+    header("retry-after-ms")
+        .map(|ms| ms / 1000.)
+        .or_else(|| header("retry-after"))
+        .map(Duration::from_secs_f64)
+}
+
+// endregion: --- Retry After
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Result<T> = core::result::Result<T, Error>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_retry_after_from_msg_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let fx_msgs = [
+            ("Rate limit reached. Please try again in 20ms. Visit ...", Some(20)),
+            ("Rate limit reached. Please try again in 1.5s.", Some(1500)),
+            ("Rate limit reached. Please try again in 2m10s.", Some(130_000)),
+            ("Rate limit reached. Please try again in 1h2m0.5s.", Some(3_720_500)),
+            ("Rate limit reached. Please try again in 1s500ms.", Some(1500)),
+            ("You exceeded your current quota.", None),
+        ];
+
+        for (msg, expected_ms) in fx_msgs {
+            // -- Exec
+            let retry_after = retry_after_from_msg(msg);
+
+            // -- Check
+            assert_eq!(retry_after.map(|d| d.as_millis() as u64), expected_ms, "{msg}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_retry_delay_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let policy = RetryPolicy {
+            max_retries: 10,
+            base_delay_ms: 100,
+            max_delay_ms: 1000,
+        };
+
+        // -- Exec & Check
+        for _ in 0..20 {
+            let first = policy.delay(1, None).as_millis();
+            assert!((50..=100).contains(&first), "{first}");
+            let third = policy.delay(3, None).as_millis();
+            assert!((200..=400).contains(&third), "{third}");
+            let capped = policy.delay(10, None).as_millis();
+            assert!((500..=1000).contains(&capped), "{capped}");
+        }
+        let hinted = policy.delay(1, Some(Duration::from_secs(5)));
+        assert_eq!(hinted, Duration::from_secs(5));
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
@@ -0,0 +1,141 @@
+//! Minimal Server-Sent Events parser for streamed API responses.
+
+// region:    --- Types
+
+#[derive(Debug, Default, PartialEq)]
+pub struct SseEvent {
+    pub event: String,
+    pub data: String,
+}
+
+/// Accumulates raw bytes and yields the complete events.
+#[derive(Debug, Default)]
+pub struct SseParser {
+    buf: Vec<u8>,
+}
+
+// endregion: --- Types
+
+// region:    --- Parser
+
+impl SseParser {
+    /// Pushes a chunk of the body and returns the events completed by it.
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<SseEvent> {
+        self.buf.extend_from_slice(chunk);
+
+        // Only complete blocks are decoded, so a multi-byte char split
+        // across chunks is never cut in half.
+        let mut events = Vec::new();
+        while let Some((idx, sep_len)) = find_block_end(&self.buf) {
+            let block: Vec<u8> = self.buf.drain(..idx + sep_len).collect();
+            if let Some(event) = parse_block(&String::from_utf8_lossy(&block)) {
+                events.push(event);
+            }
+        }
+
+        events
+    }
+}
+
+/// Returns the start and length of the first blank line separator
+/// (`\n\n` or `\r\n\r\n`).
+fn find_block_end(buf: &[u8]) -> Option<(usize, usize)> {
+    let lf = find_bytes(buf, b"\n\n").map(|idx| (idx, 2));
+    let crlf = find_bytes(buf, b"\r\n\r\n").map(|idx| (idx, 4));
+
+    match (lf, crlf) {
+        (Some(lf), Some(crlf)) => Some(if crlf.0 < lf.0 { crlf } else { lf }),
+        (lf, crlf) => lf.or(crlf),
+    }
+}
+
+fn find_bytes(buf: &[u8], needle: &[u8]) -> Option<usize> {
+    buf.windows(needle.len()).position(|w| w == needle)
+}
+
+fn parse_block(block: &str) -> Option<SseEvent> {
+    let mut event = SseEvent::default();
+    let mut data_lines = Vec::new();
+
+    for line in block.lines() {
+        if let Some(val) = line.strip_prefix("event:") {
+            event.event = val.trim().to_string();
+        } else if let Some(val) = line.strip_prefix("data:") {
+            data_lines.push(val.strip_prefix(' ').unwrap_or(val));
+        }
+    }
+
+    if event.event.is_empty() && data_lines.is_empty() {
+        return None;
+    }
+    event.data = data_lines.join("\n");
+
+    Some(event)
+}
+
+// endregion: --- Parser
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Result<T> = core::result::Result<T, Error>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_sse_parser_split_chunks_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mut parser = SseParser::default();
+
+        // -- Exec
+        let first = parser.push(b"event: thread.message.delta\ndata: {\"a\"");
+        let second = parser.push(b":1}\n\nevent: done\r\ndata: [DONE]\r\n\r\n");
+
+        // -- Check
+        assert!(first.is_empty());
+        assert_eq!(
+            second,
+            vec![
+                SseEvent {
+                    event: "thread.message.delta".into(),
+                    data: "{\"a\":1}".into(),
+                },
+                SseEvent {
+                    event: "done".into(),
+                    data: "[DONE]".into(),
+                },
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sse_parser_split_multibyte_char_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let mut parser = SseParser::default();
+        let body = "data: caf\u{e9} \u{1f980}\n\n".as_bytes();
+        // Split inside the 4-byte crab emoji.
+        let split = body.len() - 4;
+
+        // -- Exec
+        let first = parser.push(&body[..split]);
+        let second = parser.push(&body[split..]);
+
+        // -- Check
+        assert!(first.is_empty());
+        assert_eq!(
+            second,
+            vec![SseEvent {
+                event: String::new(),
+                data: "caf\u{e9} \u{1f980}".into(),
+            }]
+        );
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
@@ -0,0 +1,98 @@
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+// region:    --- Constants
+
+pub const DEFAULT_DIR: &str = "rusty_ai";
+
+// endregion: --- Constants
+
+// region:    --- Args
+
+/// CLI AI assistant for your project, using the OpenAI API.
+///
+/// Without command, starts the interactive prompt.
+#[derive(Debug, Parser)]
+#[command(name = "rusty-ai", version)]
+pub struct Args {
+    /// Config directory (with the rusty_ai.toml)
+    #[arg(long, global = true, default_value = DEFAULT_DIR)]
+    pub dir: PathBuf,
+
+    /// Conversation name (default conversation if absent)
+    #[arg(long, global = true)]
+    pub conv: Option<String>,
+
+    /// Re-upload the bundles when their source files change (interactive prompt)
+    #[arg(long)]
+    pub watch: bool,
+
+    /// Plain text output, no markdown rendering nor colors (also with NO_COLOR
+    /// or when stdout is not a terminal)
+    #[arg(long, global = true)]
+    pub plain: bool,
+
+    #[command(subcommand)]
+    pub cmd: Option<SubCmd>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SubCmd {
+    /// Asks one question and prints the answer
+    Ask {
+        /// The question (read from stdin if absent or "-")
+        question: Option<String>,
+        /// Compose the question in $EDITOR
+        #[arg(long, conflicts_with = "question")]
+        edit: bool,
+        /// Max seconds for the answer, cancelled after (overrides `run_timeout_secs`)
+        #[arg(long, value_name = "SECS")]
+        timeout: Option<u64>,
+    },
+    /// Exports the conversation history (.md or .json file)
+    Export { file: PathBuf },
+    /// Manages the assistants of the account
+    Assistants {
+        #[command(subcommand)]
+        cmd: AsstsCmd,
+    },
+    /// Deletes the uploaded files not used by any assistant or bundle
+    Gc {
+        /// Delete without confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum AsstsCmd {
+    /// Lists all the assistants (newest first)
+    List,
+    /// Shows the assistants with this name or id
+    Show { name_or_id: String },
+    /// Deletes the assistants with this name or id (and their files)
+    Delete {
+        name_or_id: String,
+        /// Only show what would be deleted
+        #[arg(long)]
+        dry_run: bool,
+        /// Delete without confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+    /// Deletes the older duplicates of each assistant name (keeps the newest)
+    Prune {
+        /// Only prune the assistants with this name
+        #[arg(long)]
+        name: Option<String>,
+        /// Only show what would be deleted
+        #[arg(long)]
+        dry_run: bool,
+        /// Delete without confirmation
+        #[arg(long, short)]
+        yes: bool,
+    },
+}
+
+// endregion: --- Args
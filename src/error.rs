@@ -1,3 +1,368 @@
+use std::path::PathBuf;
+use std::process::ExitStatus;
+use std::time::{Duration, SystemTimeError};
+
+use async_openai::error::OpenAIError;
+use async_openai::types::RunStatus;
+use derive_more::{Display, From};
+
+use crate::ais::retry::retry_after_from_msg;
+
 pub type Result<T> = core::result::Result<T, Error>;
 
-pub type Error = Box<dyn std::error::Error>;
\ No newline at end of file
+#[derive(Debug, From)]
+pub enum Error {
+    // -- Config
+    /// The `rusty_ai.toml` cannot be parsed.
+    Config { file: PathBuf, cause: String },
+    MissingApiKey { env_name: &'static str },
+    /// The api key was refused (401).
+    Auth { message: String },
+
+    // -- AI
+    /// Error response of the OpenAI (compatible) API, or request failure.
+    Api { status: Option<u16>, message: String },
+    /// Rate limited (429), with the retry-after hint of the API if any.
+    RateLimited { message: String, retry_after: Option<Duration> },
+    /// The API could not be reached (connection or timeout).
+    Network(String),
+    /// A response body which is not the API JSON, e.g., the HTML page of a
+    /// gateway 502/503/504 (its status dropped by async-openai).
+    BadResponse(String),
+    /// Still failing after the retries of the `RetryPolicy`.
+    RetriesExhausted { retries: u32, cause: Box<Error> },
+    /// The run ended without completing.
+    RunFailed { status: RunStatus, last_error: Option<String> },
+    RunStreamEnded,
+    /// The run requires an action without function call to answer (cancelled).
+    NoToolOutputs,
+    /// Ctrl-C while waiting for the run (cancelled).
+    Interrupted,
+    /// The run still active after its cancel.
+    RunNotCancelled { run_id: String },
+    /// The run was not done in time (and was cancelled).
+    RunTimeout { timeout: Duration },
+    ThreadNotFound { thread_id: String },
+    AsstNotFound { name_or_id: String },
+    NoMessage,
+    /// A message content other than text (e.g., image file).
+    MsgContentNotText,
+    /// The chat backend does not upload files.
+    NoUploadedFile { file_id: String },
+
+    // -- Conversations
+    ConvExists { name: String },
+    ConvNotFound { name: String },
+    /// The conversation file cannot be parsed (kept as is).
+    ConvInvalid { name: String, cause: String },
+    InvalidConvName { name: String },
+    /// The export file is neither `.md` nor `.json`.
+    ExportFormat { file: PathBuf },
+
+    // -- Tools
+    NoToolHandler { name: String },
+    ToolArgMissing { name: &'static str },
+    ToolArgInvalid { name: &'static str, expected: &'static str },
+    InvalidRegex(regex::Error),
+    NoSearchIndex,
+    /// A tool path which does not exist (relative to the project root).
+    PathNotFound { path: String },
+    PathNotFile { path: String },
+    PathNotDir { path: String },
+    PathOutOfRoot { path: String },
+    PathExcluded { path: String },
+    CargoTestNotAllowed,
+    NoCargoProject { dir: PathBuf },
+    CargoStart(std::io::Error),
+    /// A blocking task (e.g., tool call) which panicked or was cancelled.
+    TaskFailed { task: String, cause: String },
+
+    // -- Patch
+    DiffNoHunk,
+    DiffHunkHeader { header: String },
+    DiffHunkWithoutFile { line: String },
+    DiffFileDeletion { file: String },
+    HunkMismatch { header: String },
+    PatchFileExists { path: String },
+    PatchFileNotFound { path: String },
+    /// A patch path under none of the bundle `src_dir`.
+    PatchOutOfBundles { path: String },
+    /// The undo declined, these files having changed since the patch.
+    UndoFilesChanged { files: Vec<PathBuf> },
+
+    // -- Cli
+    /// Ctrl-C during a command other than a chat (stopped).
+    CmdInterrupted,
+    NoQuestion,
+    EmptyQuestion,
+    NoAnswer,
+    NoDiff,
+    NoCodeBlock,
+    CodeBlockNotFound { num: usize, count: usize },
+    EditorStart { editor: String, cause: std::io::Error },
+    EditorFailed { editor: String, status: ExitStatus },
+    PipeStdin { command: String },
+
+    // -- Files
+    FileNotFound(PathBuf),
+    FileIo { op: FileOp, file: PathBuf, cause: std::io::Error },
+    /// A bundle source is not a file (anymore).
+    BundleSource(PathBuf),
+    /// A file to clean up which is not in a `.rusty_ai` dir (safeguard).
+    UnsafeDelete(PathBuf),
+
+    // -- Externals
+    #[from]
+    Io(std::io::Error),
+    #[from]
+    Json(serde_json::Error),
+    #[from]
+    Glob(globset::Error),
+    #[from]
+    Dialoguer(dialoguer::Error),
+    #[from]
+    Readline(rustyline::error::ReadlineError),
+    #[from]
+    Notify(notify::Error),
+    #[from]
+    Clock(SystemTimeError),
+}
+
+#[derive(Debug, Clone, Copy, Display)]
+pub enum FileOp {
+    #[display(fmt = "read")]
+    Read,
+    #[display(fmt = "write")]
+    Write,
+    #[display(fmt = "delete")]
+    Delete,
+    #[display(fmt = "rename")]
+    Rename,
+    #[display(fmt = "create the dir")]
+    CreateDir,
+}
+
+impl Error {
+    /// True when retrying or going on cannot help (e.g., bad config, broken terminal),
+    /// so the interactive prompt exits.
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            Self::Config { .. }
+                | Self::MissingApiKey { .. }
+                | Self::Auth { .. }
+                | Self::Dialoguer(_)
+                | Self::Readline(_)
+        )
+    }
+
+    /// True when the same request might succeed later
+    /// (rate limit, server or network error).
+    pub fn is_transient(&self) -> bool {
+        match self {
+            Self::RateLimited { .. } | Self::Network(_) | Self::BadResponse(_) => true,
+            Self::Api { status: Some(status), .. } => *status == 408 || *status >= 500,
+            _ => false,
+        }
+    }
+}
+
+// region:    --- Froms
+
+impl From<OpenAIError> for Error {
+    fn from(val: OpenAIError) -> Self {
+        match val {
+            OpenAIError::ApiError(api_error) => {
+                let code = api_error.code.as_ref().and_then(|c| c.as_str());
+                if code == Some("rate_limit_exceeded") {
+                    Self::RateLimited {
+                        retry_after: retry_after_from_msg(&api_error.message),
+                        message: api_error.message,
+                    }
+                } else if code == Some("invalid_api_key") {
+                    Self::Auth { message: api_error.message }
+                } else {
+                    // Note: async-openai drops the HTTP status, `server_error` is the 5xx type.
+                    let is_server_error = api_error.r#type.as_deref() == Some("server_error");
+                    Self::Api {
+                        status: is_server_error.then_some(500),
+                        message: api_error.message,
+                    }
+                }
+            }
+            OpenAIError::Reqwest(err) => err.into(),
+            OpenAIError::JSONDeserialize(err) => Self::BadResponse(err.to_string()),
+            other => Self::Api {
+                status: None,
+                message: other.to_string(),
+            },
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(val: reqwest::Error) -> Self {
+        if val.is_connect() || val.is_timeout() {
+            Self::Network(val.to_string())
+        } else {
+            Self::Api {
+                status: val.status().map(|s| s.as_u16()),
+                message: val.to_string(),
+            }
+        }
+    }
+}
+
+// endregion: --- Froms
+
+// region:    --- Error Boilerplate
+
+impl core::fmt::Display for Error {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Self::Config { file, cause } => {
+                write!(fmt, "Invalid config '{}': {cause}", file.display())
+            }
+            Self::MissingApiKey { env_name } => write!(
+                fmt,
+                "No {env_name} env variable. Please set it (or set `api_base` in rusty_ai.toml)."
+            ),
+            Self::Auth { message } => write!(fmt, "Authentication failed: {message}"),
+
+            Self::Api { status: Some(status), message } => {
+                write!(fmt, "OpenAI API error ({status}): {message}")
+            }
+            Self::Api { status: None, message } => write!(fmt, "OpenAI API error: {message}"),
+            Self::RateLimited { message, .. } => write!(fmt, "Rate limited: {message}"),
+            Self::Network(message) => write!(fmt, "Network error: {message}"),
+            Self::BadResponse(message) => {
+                write!(fmt, "Unexpected API response (e.g., gateway error page): {message}")
+            }
+            Self::RetriesExhausted { retries, cause } => {
+                write!(fmt, "{cause} (after {retries} retries)")
+            }
+            Self::RunFailed { status, last_error } => {
+                let status = format!("{status:?}").to_lowercase();
+                match last_error {
+                    Some(last_error) => write!(fmt, "Run {status}: {last_error}"),
+                    None => write!(fmt, "Run {status}"),
+                }
+            }
+            Self::RunStreamEnded => write!(fmt, "Run stream ended before run completion"),
+            Self::NoToolOutputs => {
+                write!(fmt, "The run requires an action without function call (run cancelled)")
+            }
+            Self::Interrupted => write!(fmt, "Interrupted (run cancelled)"),
+            Self::RunNotCancelled { run_id } => write!(fmt, "Run '{run_id}' still active after cancel"),
+            Self::RunTimeout { timeout } => write!(
+                fmt,
+                "Run cancelled, not done after {}s (see `run_timeout_secs` or `--timeout`)",
+                timeout.as_secs()
+            ),
+            Self::ThreadNotFound { thread_id } => write!(
+                fmt,
+                "Thread '{thread_id}' not found (start the conversation again with :rc)"
+            ),
+            Self::AsstNotFound { name_or_id } => {
+                write!(fmt, "Assistant '{name_or_id}' not found")
+            }
+            Self::NoMessage => write!(fmt, "No message found"),
+            Self::MsgContentNotText => write!(fmt, "Message image not supported yet"),
+            Self::NoUploadedFile { file_id } => {
+                write!(fmt, "No uploaded file '{file_id}' with the chat backend")
+            }
+
+            Self::ConvExists { name } => write!(fmt, "Conversation '{name}' already exists"),
+            Self::ConvNotFound { name } => {
+                write!(fmt, "No conversation '{name}' (create it with :conv new)")
+            }
+            Self::ConvInvalid { name, cause } => {
+                write!(fmt, "Conversation '{name}' cannot be read ({cause})")
+            }
+            Self::InvalidConvName { name } => write!(
+                fmt,
+                "Invalid conversation name '{name}' (letters, digits, - and _ only)"
+            ),
+            Self::ExportFormat { file } => {
+                write!(fmt, "Export file '{}' must be .md or .json", file.display())
+            }
+
+            Self::NoToolHandler { name } => write!(fmt, "No local handler for the tool '{name}'"),
+            Self::ToolArgMissing { name } => write!(fmt, "Missing '{name}' argument"),
+            Self::ToolArgInvalid { name, expected } => write!(fmt, "'{name}' must be {expected}"),
+            Self::InvalidRegex(err) => write!(fmt, "Invalid regex: {err}"),
+            Self::NoSearchIndex => write!(fmt, "No search index yet"),
+            Self::PathNotFound { path } => write!(fmt, "No file or directory '{path}'"),
+            Self::PathNotFile { path } => write!(fmt, "'{path}' is not a file"),
+            Self::PathNotDir { path } => write!(fmt, "'{path}' is not a directory"),
+            Self::PathOutOfRoot { path } => write!(fmt, "'{path}' is out of the project root"),
+            Self::PathExcluded { path } => write!(fmt, "'{path}' is excluded"),
+            Self::CargoTestNotAllowed => write!(
+                fmt,
+                "cargo test is not allowed (see `allow_test` in the [cargo] config)"
+            ),
+            Self::NoCargoProject { dir } => {
+                write!(fmt, "No Cargo.toml in '{}' or its parents", dir.display())
+            }
+            Self::CargoStart(err) => write!(fmt, "Cannot run cargo: {err}"),
+            Self::TaskFailed { task, cause } => write!(fmt, "{task} task failed: {cause}"),
+
+            Self::DiffNoHunk => write!(fmt, "No hunk in the diff"),
+            Self::DiffHunkHeader { header } => write!(fmt, "Invalid hunk header: {header}"),
+            Self::DiffHunkWithoutFile { line } => write!(fmt, "Hunk without file header: {line}"),
+            Self::DiffFileDeletion { file } => {
+                write!(fmt, "File deletion is not supported ('{file}')")
+            }
+            Self::HunkMismatch { header } => write!(fmt, "Hunk '{header}' does not match the file"),
+            Self::PatchFileExists { path } => write!(fmt, "'{path}' already exists"),
+            Self::PatchFileNotFound { path } => write!(fmt, "'{path}' does not exist"),
+            Self::PatchOutOfBundles { path } => {
+                write!(fmt, "'{path}' is not under a bundle src_dir")
+            }
+            Self::UndoFilesChanged { files } => {
+                let files: Vec<String> = files.iter().map(|f| format!("'{}'", f.display())).collect();
+                write!(fmt, "Not undone, changed since the patch: {}", files.join(", "))
+            }
+
+            Self::CmdInterrupted => write!(fmt, "Interrupted"),
+            Self::NoQuestion => write!(fmt, "No question (as argument or piped in stdin)"),
+            Self::EmptyQuestion => write!(fmt, "Empty question, nothing sent"),
+            Self::NoAnswer => write!(fmt, "No answer yet"),
+            Self::NoDiff => write!(fmt, "No diff in the last answer"),
+            Self::NoCodeBlock => write!(fmt, "No code block in the last answer"),
+            Self::CodeBlockNotFound { num, count } => {
+                write!(fmt, "No code block {num} (1 to {count})")
+            }
+            Self::EditorStart { editor, cause } => {
+                write!(fmt, "Cannot start the editor '{editor}': {cause} (see $EDITOR)")
+            }
+            Self::EditorFailed { editor, status } => {
+                write!(fmt, "Editor '{editor}' failed ({status}), nothing sent")
+            }
+            Self::PipeStdin { command } => write!(fmt, "Cannot write to the stdin of `{command}`"),
+
+            Self::FileNotFound(file) => write!(fmt, "File not found: {}", file.display()),
+            Self::FileIo { op, file, cause } => {
+                write!(fmt, "Cannot {op} '{}': {cause}", file.display())
+            }
+            Self::BundleSource(file) => {
+                write!(fmt, "Cannot bundle '{}', it is not a file", file.display())
+            }
+            Self::UnsafeDelete(file) => {
+                write!(fmt, "Should not delete '{}', not in a .rusty_ai dir", file.display())
+            }
+
+            Self::Io(err) => write!(fmt, "I/O error: {err}"),
+            Self::Json(err) => write!(fmt, "JSON error: {err}"),
+            Self::Glob(err) => write!(fmt, "Invalid glob: {err}"),
+            Self::Dialoguer(err) => write!(fmt, "Prompt error: {err}"),
+            Self::Readline(err) => write!(fmt, "Prompt error: {err}"),
+            Self::Notify(err) => write!(fmt, "Watch error: {err}"),
+            Self::Clock(err) => write!(fmt, "Clock error: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+// endregion: --- Error Boilerplate
@@ -1,110 +1,237 @@
 // region:    --- Modules
 
-use textwrap::wrap;
-
-use crate::rusty_ai::RustyAI;
-use crate::utils::cli::{prompt, txt_res, ico_res};
+use std::io::{self, IsTerminal, Read, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use clap::Parser;
+
+use crate::args::{Args, AsstsCmd, SubCmd};
+use crate::ais::backend::AsstInfo;
+use crate::repl::Repl;
+use crate::rusty_ai::{gc, AsstAdmin, RustyAI};
+use crate::utils::cli::{confirm, ico_res, ico_deleted_ok, fmt_time};
+use crate::utils::editor::compose_message;
+use crate::utils::render::Renderer;
 pub use self::ais::new_oa_client;
-pub use self::error::{Error, Result};
+pub use self::error::{Error, FileOp, Result};
 
 mod error;
+mod args;
 mod ais;
+mod patch;
+mod repl;
+mod retrieval;
 mod rusty_ai;
+mod tools;
 mod utils;
+#[cfg(test)]
+mod test_support;
 
 // endregion: --- Modules
 
-#[tokio::main]
-async fn main() {
-   println!();
+/// Exit code when the error is fatal (e.g., bad config), see `Error::is_fatal`.
+const EXIT_FATAL: u8 = 2;
 
-   match start().await {
-       Ok(_) => println!("\n{} Bye, See you\n", ico_res()),
-       Err(e) => println!("\nError: {}\n", e),
+#[tokio::main]
+async fn main() -> ExitCode {
+   let args = Args::parse();
+   let conv_name = args.conv.as_deref();
+   // Note: first, as it disables the colors with `--plain` or NO_COLOR.
+   let renderer = Renderer::new(args.plain);
+
+   let res = match args.cmd {
+       Some(SubCmd::Ask { question, edit, timeout }) => {
+           ask(&args.dir, conv_name, question, edit, timeout, &renderer).await
+       }
+       Some(SubCmd::Export { file }) => export(&args.dir, conv_name, &file).await,
+       Some(SubCmd::Assistants { cmd }) => assistants(&args.dir, cmd).await,
+       Some(SubCmd::Gc { yes }) => gc(&args.dir, yes).await,
+       None => {
+           println!();
+           start(&args.dir, conv_name, args.watch, renderer).await
+               .map(|_| println!("\n{} Bye, See you\n", ico_res()))
+       }
+   };
+
+   match res {
+       Ok(_) => ExitCode::SUCCESS,
+       Err(e) => {
+           eprintln!("\nError: {}\n", e);
+           if e.is_fatal() {
+               ExitCode::from(EXIT_FATAL)
+           } else {
+               ExitCode::FAILURE
+           }
+       }
    }
 }
 
-// region:    --- Types
-
-#[derive(Debug)]
-enum Cmd {
-    Quit,
-    Chat(String),
-    RefreshAll,
-    RefreshConv,
-    RefreshInst,
-    RefreshFiles,
-    Help,
+/// Interactive prompt.
+async fn start(dir: &Path, conv_name: Option<&str>, watch: bool, renderer: Renderer) -> Result<()> {
+    let repl = Repl::start(dir, conv_name, watch, renderer).await?;
+
+    repl.run().await
 }
 
-impl  Cmd {
-    fn from_input(intput: impl Into<String>) -> Self {
-        let input = intput.into();
-
-        if input == ":q" {
-            Self::Quit
-        } else if input == ":ra" || input == ":RA" {
-            Self::RefreshAll
-        } else if input == ":ri" || input == ":RI" {
-            Self::RefreshInst
-        } else if input == ":rf" || input == ":RF" {
-            Self::RefreshFiles
-        } else if input == ":rc" || input == ":RC" {
-            Self::RefreshConv
-        } else if input == ":h" || input == ":H" {
-            Self::Help
+/// One shot question, answer printed on stdout (status on stderr), as is
+/// when the `renderer` is plain (e.g., piped).
+/// - `edit` composes the question in the editor (instead of `question`).
+/// - `timeout` (seconds) overrides the `run_timeout_secs` of the config.
+async fn ask(
+    dir: &Path,
+    conv_name: Option<&str>,
+    question: Option<String>,
+    edit: bool,
+    timeout: Option<u64>,
+    renderer: &Renderer,
+) -> Result<()> {
+    // -- Get the question (from the editor, or stdin when absent or "-")
+    let question = match question.filter(|q| q != "-") {
+        _ if edit => compose_message(None)?.ok_or(Error::EmptyQuestion)?,
+        Some(question) => question,
+        None if !io::stdin().is_terminal() => {
+            let mut question = String::new();
+            io::stdin().read_to_string(&mut question)?;
+            question
+        }
+        None => return Err(Error::NoQuestion),
+    };
+    if question.trim().is_empty() {
+        return Err(Error::EmptyQuestion);
+    }
+
+    let mut rusty_ai = RustyAI::init_from_dir(dir, false).await?;
+    if let Some(timeout) = timeout {
+        rusty_ai.set_run_timeout(Duration::from_secs(timeout));
+    }
+    let mut conv = rusty_ai.load_or_create_conv(conv_name, false).await?;
+
+    if rusty_ai.stream() && renderer.is_plain() {
+        let (_, interrupted) = rusty_ai.chat_stream(&mut conv, &question, &mut |delta| {
+            print!("{delta}");
+            let _ = io::stdout().flush();
+        }).await?;
+        println!();
+        if interrupted {
+            return Err(Error::Interrupted);
+        }
+    } else if rusty_ai.stream() {
+        let mut lines = renderer.lines();
+        let (_, interrupted) = rusty_ai.chat_stream(&mut conv, &question, &mut |delta| {
+            print!("{}", lines.push(delta));
+            let _ = io::stdout().flush();
+        }).await?;
+        println!("{}", lines.finish());
+        if interrupted {
+            return Err(Error::Interrupted);
+        }
+    } else {
+        let res = rusty_ai.chat(&mut conv, &question).await?;
+        if renderer.is_plain() {
+            println!("{res}");
         } else {
-            Self::Chat(input)
+            println!("{}", renderer.render(&res));
         }
     }
+
+    Ok(())
 }
 
-// endregion: --- Types
+/// Exports the conversation history to `file` (.md or .json).
+async fn export(dir: &Path, conv_name: Option<&str>, file: &Path) -> Result<()> {
+    let rusty_ai = RustyAI::init_from_dir(dir, false).await?;
+    let conv = rusty_ai.load_or_create_conv(conv_name, false).await?;
 
-const DEFAULT_DIR: &str = "rusty_ai";
+    rusty_ai.export_conv(&conv, file).await
+}
 
-async fn start() -> Result<()> {
-    let mut rusty_ai = RustyAI::init_from_dir(DEFAULT_DIR, false).await?;
+/// The `assistants` subcommands.
+async fn assistants(dir: &Path, cmd: AsstsCmd) -> Result<()> {
+    let admin = AsstAdmin::from_dir(dir)?;
 
-    let mut conv = rusty_ai.load_or_create_conv(false).await?;
-    
-    loop {
-        println!();
-        let input = prompt("rusty-ai query")?;
-        let cmd = Cmd::from_input(input);
-
-        match cmd {
-            Cmd::Quit => break,
-            Cmd::Chat(msg) => {
-                let res = rusty_ai.chat(&conv, &msg).await?;
-                let res = wrap(&res, 80).join("\n");
-                println!("{} {}",  ico_res(), txt_res(res));
-            },
-            Cmd::RefreshAll => {
-                rusty_ai = RustyAI::init_from_dir(DEFAULT_DIR, true).await?;
-                conv = rusty_ai.load_or_create_conv(true).await?;
-            },
-            Cmd::RefreshConv => {
-                conv = rusty_ai.load_or_create_conv(true).await?;
-            },
-            Cmd::RefreshInst => {
-                rusty_ai.upload_instructions().await?;
-                conv = rusty_ai.load_or_create_conv(true).await?;
-            },
-            Cmd::RefreshFiles => {
-                rusty_ai.upload_files(true).await?;
-                conv = rusty_ai.load_or_create_conv(true).await?;
-            }, 
-            Cmd::Help => {
-                println!(
-                    "{} :ra - refresh all\n{} :ri - refresh instructions\n{} :rf - refresh files\n{} :rc - refresh converstion\n{} :h  - help\n{} :q  - quit",
-                    ico_res(), ico_res(), ico_res(), ico_res(), ico_res(), ico_res()
-                );
+    match cmd {
+        AsstsCmd::List => {
+            let assts = admin.list().await?;
+            if assts.is_empty() {
+                println!("{} No assistant", ico_res());
+            }
+            for asst in assts.iter() {
+                print_asst(asst, false);
+            }
+        }
+        AsstsCmd::Show { name_or_id } => {
+            let assts = admin.find(&name_or_id).await?;
+            if assts.is_empty() {
+                return Err(Error::AsstNotFound { name_or_id });
+            }
+            for asst in assts.iter() {
+                print_asst(asst, true);
             }
         }
+        AsstsCmd::Delete { name_or_id, dry_run, yes } => {
+            let to_delete = admin.find(&name_or_id).await?;
+            if to_delete.is_empty() {
+                return Err(Error::AsstNotFound { name_or_id });
+            }
+            delete_assts(&admin, &to_delete, dry_run, yes).await?;
+        }
+        AsstsCmd::Prune { name, dry_run, yes } => {
+            let to_delete = admin.stale(name.as_deref()).await?;
+            if to_delete.is_empty() {
+                println!("{} Nothing to prune", ico_res());
+            }
+            delete_assts(&admin, &to_delete, dry_run, yes).await?;
+        }
     }
 
-    // println!("\n{} rusty_ai {} - conv {conv:?}", ico_res(), rusty_ai.name());
-    
     Ok(())
-}
\ No newline at end of file
+}
+
+/// Lists the assistants to delete and deletes them after confirmation
+/// (or directly if `yes`), unless `dry_run`.
+async fn delete_assts(admin: &AsstAdmin, assts: &[AsstInfo], dry_run: bool, yes: bool) -> Result<()> {
+    print_deleted(assts, true);
+    if dry_run || assts.is_empty() {
+        return Ok(());
+    }
+
+    let question = format!("Delete these {} assistants (and their files)?", assts.len());
+    if yes || confirm(&question)? {
+        admin.delete_assts(assts).await?;
+        print_deleted(assts, false);
+    }
+
+    Ok(())
+}
+
+fn print_asst(asst: &AsstInfo, with_files: bool) {
+    println!(
+        "{} {}  {}  {}  {}  ({} files)",
+        ico_res(),
+        asst.id,
+        asst.name.as_deref().unwrap_or("-"),
+        asst.model,
+        fmt_time(asst.created_at),
+        asst.file_names.len()
+    );
+    if with_files {
+        for file_name in asst.file_names.iter() {
+            println!("    - {file_name}");
+        }
+    }
+}
+
+fn print_deleted(assts: &[AsstInfo], dry_run: bool) {
+    let action = if dry_run { "Would delete" } else { "Deleted" };
+    for asst in assts {
+        println!(
+            "{} {action} {}  {}  ({})",
+            ico_deleted_ok(),
+            asst.id,
+            asst.name.as_deref().unwrap_or("-"),
+            fmt_time(asst.created_at)
+        );
+    }
+}
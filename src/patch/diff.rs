@@ -0,0 +1,349 @@
+//! Unified diff parsing and hunk application.
+
+use crate::{Error, Result};
+
+// region:    --- Types
+
+/// The changes of one file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilePatch {
+    /// As in the `+++` header (without the `b/` prefix).
+    pub path: String,
+    /// The `---` header is `/dev/null`.
+    pub is_new: bool,
+    pub hunks: Vec<Hunk>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hunk {
+    /// The `@@ -l,n +l,n @@` line.
+    pub header: String,
+    /// First line of the hunk in the original file (from 1, 0 for a new file).
+    pub old_start: usize,
+    pub lines: Vec<HunkLine>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HunkLine {
+    Context(String),
+    Removed(String),
+    Added(String),
+}
+
+impl Hunk {
+    fn old_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                HunkLine::Context(l) | HunkLine::Removed(l) => Some(l.as_str()),
+                HunkLine::Added(_) => None,
+            })
+            .collect()
+    }
+
+    fn new_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|line| match line {
+                HunkLine::Context(l) | HunkLine::Added(l) => Some(l.as_str()),
+                HunkLine::Removed(_) => None,
+            })
+            .collect()
+    }
+}
+
+// endregion: --- Types
+
+// region:    --- Extract & Parse
+
+/// Returns the ```` ```diff ```` (or `patch`) code blocks of the markdown
+/// `text`, or the whole `text` if it is a raw diff.
+/// Note: a block only ends on a bare ```` ``` ```` with the indent of its opening
+///       fence, as the diff lines can contain fences (e.g., of a markdown file).
+pub fn extract_diffs(text: &str) -> Option<String> {
+    let mut diffs = Vec::new();
+    // (indent of the opening fence, block lines)
+    let mut block: Option<(&str, Vec<&str>)> = None;
+
+    for line in text.lines() {
+        let fence = line.trim_start();
+        let indent = &line[..line.len() - fence.len()];
+        match block.as_mut() {
+            Some((open_indent, lines)) if line.trim_end() == format!("{open_indent}```") => {
+                diffs.push(lines.join("\n"));
+                block = None;
+            }
+            Some((_, lines)) => lines.push(line),
+            None if fence.trim_end() == "```diff" || fence.trim_end() == "```patch" => {
+                block = Some((indent, Vec::new()))
+            }
+            None => (),
+        }
+    }
+
+    if !diffs.is_empty() {
+        Some(diffs.join("\n"))
+    } else if text.starts_with("--- ") || text.contains("\n+++ ") {
+        Some(text.to_string())
+    } else {
+        None
+    }
+}
+
+/// Parses a unified diff (one or more files).
+/// Note: the hunk line counts are ignored (often wrong in generated diffs),
+///       a hunk goes until the next hunk or file header.
+pub fn parse_diff(diff: &str) -> Result<Vec<FilePatch>> {
+    let lines: Vec<&str> = diff.lines().collect();
+    let mut patches: Vec<FilePatch> = Vec::new();
+    let mut idx = 0;
+
+    while idx < lines.len() {
+        let line = lines[idx];
+        let next = lines.get(idx + 1).copied().unwrap_or_default();
+
+        // -- File header
+        if let (Some(old), Some(new)) = (line.strip_prefix("--- "), next.strip_prefix("+++ ")) {
+            let (old, new) = (header_path(old), header_path(new));
+            if new == "/dev/null" {
+                return Err(Error::DiffFileDeletion { file: old.to_string() });
+            }
+            patches.push(FilePatch {
+                path: new.to_string(),
+                is_new: old == "/dev/null",
+                hunks: Vec::new(),
+            });
+            idx += 2;
+            continue;
+        }
+
+        // -- Hunk
+        if line.starts_with("@@") {
+            let patch = patches
+                .last_mut()
+                .ok_or_else(|| Error::DiffHunkWithoutFile { line: line.to_string() })?;
+            let mut hunk = Hunk {
+                header: line.to_string(),
+                old_start: hunk_old_start(line)?,
+                lines: Vec::new(),
+            };
+            idx += 1;
+            while let Some(&line) = lines.get(idx) {
+                let next = lines.get(idx + 1).copied().unwrap_or_default();
+                if line.starts_with("@@") || (line.starts_with("--- ") && next.starts_with("+++ ")) {
+                    break;
+                }
+                if let Some(l) = line.strip_prefix('+') {
+                    hunk.lines.push(HunkLine::Added(l.to_string()));
+                } else if let Some(l) = line.strip_prefix('-') {
+                    hunk.lines.push(HunkLine::Removed(l.to_string()));
+                } else if !line.starts_with('\\') {
+                    // Note: blank context lines often lose their leading space.
+                    let l = line.strip_prefix(' ').unwrap_or(line);
+                    hunk.lines.push(HunkLine::Context(l.to_string()));
+                }
+                idx += 1;
+            }
+            while matches!(hunk.lines.last(), Some(HunkLine::Context(l)) if l.trim().is_empty()) {
+                hunk.lines.pop();
+            }
+            patch.hunks.push(hunk);
+            continue;
+        }
+
+        // Other lines (e.g., `diff --git`, `index`) are skipped.
+        idx += 1;
+    }
+
+    patches.retain(|p| !p.hunks.is_empty());
+    if patches.is_empty() {
+        return Err(Error::DiffNoHunk);
+    }
+
+    Ok(patches)
+}
+
+/// Returns the path of a `---`/`+++` header value (without `a/`, `b/` and timestamp).
+fn header_path(value: &str) -> &str {
+    let path = value.split('\t').next().unwrap_or_default().trim();
+    path.strip_prefix("a/")
+        .or_else(|| path.strip_prefix("b/"))
+        .unwrap_or(path)
+}
+
+/// Returns the `l` of `@@ -l,n +l,n @@`.
+fn hunk_old_start(header: &str) -> Result<usize> {
+    header
+        .split_whitespace()
+        .find_map(|part| part.strip_prefix('-'))
+        .and_then(|range| range.split(',').next())
+        .and_then(|start| start.parse().ok())
+        .ok_or_else(|| Error::DiffHunkHeader { header: header.to_string() })
+}
+
+// endregion: --- Extract & Parse
+
+// region:    --- Apply
+
+/// Returns `content` with the `hunk` applied, and the line offset for the
+/// next hunks of the same patch (0 for the first one).
+/// A hunk is applied where its original lines are, the closest to its
+/// `old_start` (the line numbers of generated diffs are often off).
+pub fn apply_hunk(content: &str, hunk: &Hunk, offset: isize) -> Result<(String, isize)> {
+    let mut lines: Vec<String> = content.lines().map(String::from).collect();
+    let old = hunk.old_lines();
+    let new = hunk.new_lines();
+    let expected = (hunk.old_start as isize - 1 + offset).max(0) as usize;
+
+    let pos = find_lines(&lines, &old, expected)
+        .ok_or_else(|| Error::HunkMismatch { header: hunk.header.clone() })?;
+    lines.splice(pos..pos + old.len(), new.iter().map(|l| l.to_string()));
+    let offset = pos as isize - (hunk.old_start as isize - 1) + new.len() as isize - old.len() as isize;
+
+    let mut res = lines.join("\n");
+    if !res.is_empty() && (content.is_empty() || content.ends_with('\n')) {
+        res.push('\n');
+    }
+
+    Ok((res, offset))
+}
+
+/// Returns the position of `old` in `lines` the closest to `expected`
+/// (trailing whitespaces ignored).
+fn find_lines(lines: &[String], old: &[&str], expected: usize) -> Option<usize> {
+    if old.is_empty() {
+        return Some(expected.min(lines.len()));
+    }
+    let last = lines.len().checked_sub(old.len())?;
+    let matches_at = |pos: usize| {
+        lines[pos..pos + old.len()]
+            .iter()
+            .zip(old)
+            .all(|(line, old)| line.trim_end() == old.trim_end())
+    };
+
+    let expected = expected.min(last);
+    (0..=last.max(expected)).find_map(|dist| {
+        let before = expected.checked_sub(dist).filter(|&p| matches_at(p));
+        before.or_else(|| Some(expected + dist).filter(|&p| p <= last && matches_at(p)))
+    })
+}
+
+// endregion: --- Apply
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Result<T> = core::result::Result<T, Error>; // For tests.
+
+    use super::*;
+
+    /// Returns `content` with the `hunks` applied (in order).
+    fn apply_hunks(content: &str, hunks: &[&Hunk]) -> crate::Result<String> {
+        let mut content = content.to_string();
+        let mut offset: isize = 0;
+        for hunk in hunks {
+            (content, offset) = apply_hunk(&content, hunk, offset)?;
+        }
+
+        Ok(content)
+    }
+
+    const FX_CONTENT: &str = "fn main() {\n    let a = 1;\n    println!(\"{a}\");\n}\n\nfn other() {}\n";
+
+    #[test]
+    fn test_extract_parse_diff_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let fx_answer = "Here is the fix:\n\n```diff\n--- a/src/main.rs\n+++ b/src/main.rs\n@@ -2,2 +2,2 @@ fn main() {\n-    let a = 1;\n+    let a = 2;\n     println!(\"{a}\");\n```\n\nAnd a new file:\n```diff\n--- /dev/null\n+++ b/src/new.rs\n@@ -0,0 +1 @@\n+pub fn new() {}\n```\n";
+
+        // -- Exec
+        let diff = extract_diffs(fx_answer).ok_or("no diff")?;
+        let patches = parse_diff(&diff)?;
+
+        // -- Check
+        assert_eq!(patches.len(), 2);
+        assert_eq!(patches[0].path, "src/main.rs");
+        assert!(!patches[0].is_new);
+        assert_eq!(patches[0].hunks[0].old_start, 2);
+        assert_eq!(
+            patches[0].hunks[0].lines,
+            [
+                HunkLine::Removed("    let a = 1;".to_string()),
+                HunkLine::Added("    let a = 2;".to_string()),
+                HunkLine::Context("    println!(\"{a}\");".to_string()),
+            ]
+        );
+        assert_eq!(patches[1].path, "src/new.rs");
+        assert!(patches[1].is_new);
+        assert!(extract_diffs("No code here.").is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_diffs_markdown_fences_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let fx_answer = "Update the readme:\n\n```diff\n--- a/README.md\n+++ b/README.md\n@@ -1,3 +1,4 @@\n ```sh\n cargo build\n+cargo test\n ```\n```\nDone.\n";
+
+        // -- Exec
+        let diff = extract_diffs(fx_answer).ok_or("no diff")?;
+        let patches = parse_diff(&diff)?;
+
+        // -- Check
+        assert_eq!(patches.len(), 1);
+        assert_eq!(patches[0].path, "README.md");
+        assert_eq!(
+            patches[0].hunks[0].lines,
+            [
+                HunkLine::Context("```sh".to_string()),
+                HunkLine::Context("cargo build".to_string()),
+                HunkLine::Added("cargo test".to_string()),
+                HunkLine::Context("```".to_string()),
+            ]
+        );
+        assert!(!diff.contains("Done."));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_hunks_offset_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        // Line numbers off by 2 (as often in generated diffs).
+        let fx_diff = "--- a/src/main.rs\n+++ b/src/main.rs\n@@ -4,3 +4,3 @@\n     let a = 1;\n-    println!(\"{a}\");\n+    println!(\"a: {a}\");\n }\n@@ -8 +8,2 @@\n fn other() {}\n+fn added() {}\n";
+        let patches = parse_diff(fx_diff)?;
+        let hunks: Vec<&Hunk> = patches[0].hunks.iter().collect();
+
+        // -- Exec
+        let res = apply_hunks(FX_CONTENT, &hunks)?;
+        let only_second = apply_hunks(FX_CONTENT, &hunks[1..])?;
+
+        // -- Check
+        assert_eq!(res, "fn main() {\n    let a = 1;\n    println!(\"a: {a}\");\n}\n\nfn other() {}\nfn added() {}\n");
+        assert!(only_second.ends_with("fn other() {}\nfn added() {}\n"));
+        assert!(only_second.contains("println!(\"{a}\")"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_apply_hunks_mismatch_err() -> Result<()> {
+        // -- Setup & Fixtures
+        let fx_diff = "--- a/src/main.rs\n+++ b/src/main.rs\n@@ -2 +2 @@\n-    let b = 1;\n+    let b = 2;\n";
+        let patches = parse_diff(fx_diff)?;
+
+        // -- Exec
+        let res = apply_hunks(FX_CONTENT, &[&patches[0].hunks[0]]);
+
+        // -- Check
+        let err = res.err().ok_or("should not match")?;
+        assert!(matches!(err, crate::Error::HunkMismatch { .. }), "{err}");
+        assert!(err.to_string().contains("does not match"), "{err}");
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
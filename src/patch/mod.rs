@@ -0,0 +1,457 @@
+//! Applies the unified diffs of the assistant answers to the bundle sources,
+//! hunk by hunk (each one accepted or not), with an undo of the last patch.
+
+// region:    --- Modules
+
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use console::style;
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, FileOp, Result};
+use crate::utils::cli::{ico_check, ico_err};
+use crate::utils::files::{ensure_dir, file_io, hash_content, load_from_json, read_to_string, save_to_json};
+
+pub use self::diff::{extract_diffs, Hunk};
+
+use self::diff::{apply_hunk, parse_diff, FilePatch, HunkLine};
+
+mod diff;
+
+// endregion: --- Modules
+
+// region:    --- Types
+
+/// Applies the patches to the files under the bundles `src_dir` only.
+#[derive(Debug)]
+pub struct Patcher {
+    /// The canonical bundles `src_dir` (the only patchable dirs)
+    src_dirs: Vec<PathBuf>,
+    /// The dirs the patch relative paths are tried from.
+    base_dirs: Vec<PathBuf>,
+    /// The undo record of the last applied patch.
+    undo_file: PathBuf,
+}
+
+/// The files of the last applied patch, as they were before.
+#[derive(Debug, Serialize, Deserialize)]
+struct UndoRecord {
+    files: Vec<UndoFile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct UndoFile {
+    path: PathBuf,
+    /// `None` for a file created by the patch.
+    original: Option<String>,
+    /// The dirs created for the file (deepest first).
+    #[serde(default)]
+    created_dirs: Vec<PathBuf>,
+    /// The hash of the patched content, to detect the later edits
+    /// (`None` for the older records).
+    #[serde(default)]
+    patched_hash: Option<String>,
+}
+
+/// A file of the patch being applied.
+struct FileChange {
+    file: PathBuf,
+    /// `None` for a file created by the patch.
+    original: Option<String>,
+    /// With the accepted hunks so far.
+    content: String,
+    /// Some hunks accepted.
+    patched: bool,
+}
+
+/// Decides if a hunk is applied (e.g., asks the user), given the file and hunk.
+pub type AcceptHunk<'a> = dyn FnMut(&Path, &Hunk) -> Result<bool> + 'a;
+
+/// Decides if the undo overwrites the files changed since the patch (e.g.,
+/// asks the user), given these files.
+pub type ConfirmUndo<'a> = dyn FnMut(&[PathBuf]) -> Result<bool> + 'a;
+
+// endregion: --- Types
+
+// region:    --- Patcher
+
+impl Patcher {
+    /// - `src_dirs` the bundles `src_dir` (joined to the config dir).
+    /// - `config_dir` also a base dir of the patch paths (e.g., `../src/main.rs`).
+    pub fn new(src_dirs: &[PathBuf], config_dir: &Path, undo_file: PathBuf) -> Self {
+        let mut base_dirs: Vec<PathBuf> = src_dirs.to_vec();
+        base_dirs.extend(src_dirs.iter().filter_map(|dir| dir.parent().map(Path::to_path_buf)));
+        base_dirs.push(config_dir.to_path_buf());
+
+        Self {
+            src_dirs: src_dirs.iter().filter_map(|dir| dir.canonicalize().ok()).collect(),
+            base_dirs,
+            undo_file,
+        }
+    }
+
+    /// Applies the accepted hunks of the `diff`, the files out of the bundles
+    /// `src_dir` being skipped. Only the hunks matching the file (with the
+    /// previous accepted hunks applied) are proposed to `accept`.
+    /// The previous undo record is replaced if some files are patched.
+    ///
+    /// Returns the patched files.
+    pub fn apply(&self, diff: &str, accept: &mut AcceptHunk<'_>) -> Result<Vec<PathBuf>> {
+        // Note: one change per file, even with several patches of the file
+        //       (e.g., several diff blocks in the answer).
+        let mut changes: Vec<FileChange> = Vec::new();
+
+        for patch in parse_diff(diff)? {
+            let file = match self.resolve(&patch) {
+                Ok(file) => file,
+                Err(err) => {
+                    println!("{} Skipped: {err}", ico_err());
+                    continue;
+                }
+            };
+
+            let idx = match changes.iter().position(|change| change.file == file) {
+                Some(idx) => idx,
+                None => {
+                    let original = if file.exists() { Some(read_to_string(&file)?) } else { None };
+                    let content = original.clone().unwrap_or_default();
+                    changes.push(FileChange { file, original, content, patched: false });
+                    changes.len() - 1
+                }
+            };
+            let change = &mut changes[idx];
+
+            let mut offset = 0;
+            for hunk in &patch.hunks {
+                match apply_hunk(&change.content, hunk, offset) {
+                    Ok((content, next_offset)) => {
+                        if accept(&change.file, hunk)? {
+                            change.content = content;
+                            change.patched = true;
+                            offset = next_offset;
+                        }
+                    }
+                    Err(err) => println!("{} Skipped in '{}': {err}", ico_err(), change.file.display()),
+                }
+            }
+        }
+
+        changes.retain(|change| change.patched);
+        if changes.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        // -- Write the files (the undo record first)
+        let record = UndoRecord {
+            files: changes
+                .iter()
+                .map(|change| UndoFile {
+                    path: change.file.clone(),
+                    original: change.original.clone(),
+                    created_dirs: missing_dirs(&change.file),
+                    patched_hash: Some(hash_content(change.content.as_bytes())),
+                })
+                .collect(),
+        };
+        save_to_json(&self.undo_file, &record)?;
+        for change in &changes {
+            if let Some(dir) = change.file.parent() {
+                ensure_dir(dir)?;
+            }
+            fs::write(&change.file, &change.content).map_err(file_io(FileOp::Write, &change.file))?;
+            println!("{} Patched '{}'", ico_check(), change.file.display());
+        }
+
+        Ok(changes.into_iter().map(|change| change.file).collect())
+    }
+
+    /// Restores the files of the last applied patch (created files deleted, and
+    /// their created dirs if empty). The files changed since the patch are only
+    /// overwritten if `confirm_changed` (otherwise `Error::UndoFilesChanged`).
+    ///
+    /// Returns the restored files (empty if nothing to undo).
+    pub fn undo(&self, confirm_changed: &mut ConfirmUndo<'_>) -> Result<Vec<PathBuf>> {
+        if !self.undo_file.is_file() {
+            return Ok(Vec::new());
+        }
+        let record: UndoRecord = load_from_json(&self.undo_file)?;
+
+        // -- Check the later edits (all the files, before restoring any)
+        let changed: Vec<PathBuf> = record
+            .files
+            .iter()
+            .filter(|file| file.is_changed())
+            .map(|file| file.path.clone())
+            .collect();
+        if !changed.is_empty() && !confirm_changed(&changed)? {
+            return Err(Error::UndoFilesChanged { files: changed });
+        }
+
+        let mut files = Vec::new();
+        for UndoFile { path, original, created_dirs, .. } in record.files {
+            match original {
+                Some(content) => fs::write(&path, content).map_err(file_io(FileOp::Write, &path))?,
+                None if path.exists() => fs::remove_file(&path).map_err(file_io(FileOp::Delete, &path))?,
+                None => (),
+            }
+            // Note: the dirs still used (e.g., by another created file) are kept.
+            for dir in created_dirs {
+                if dir.read_dir().is_ok_and(|mut entries| entries.next().is_none()) {
+                    fs::remove_dir(&dir).map_err(file_io(FileOp::Delete, &dir))?;
+                }
+            }
+            println!("{} Restored '{}'", ico_check(), path.display());
+            files.push(path);
+        }
+        fs::remove_file(&self.undo_file).map_err(file_io(FileOp::Delete, &self.undo_file))?;
+
+        Ok(files)
+    }
+
+    /// Returns the file of the `patch` (an existing one, or a new one, its
+    /// missing dirs created on write), which must be under a bundle `src_dir`.
+    fn resolve(&self, patch: &FilePatch) -> Result<PathBuf> {
+        let path = Path::new(&patch.path);
+        let candidates: Vec<PathBuf> = if path.is_absolute() {
+            vec![path.to_path_buf()]
+        } else {
+            self.base_dirs.iter().map(|dir| dir.join(path)).collect()
+        };
+        let is_allowed = |file: &PathBuf| self.src_dirs.iter().any(|dir| file.starts_with(dir));
+
+        let existing = candidates
+            .iter()
+            .filter_map(|c| c.canonicalize().ok())
+            .filter(|c| c.is_file())
+            .find(is_allowed);
+        if let Some(file) = existing {
+            if patch.is_new {
+                return Err(Error::PatchFileExists { path: patch.path.clone() });
+            }
+            return Ok(file);
+        }
+
+        // Note: the candidate with the fewest missing dirs (e.g., `src/new.rs`
+        //       from the project dir, not `src/src/new.rs` from the `src_dir`).
+        let new = candidates
+            .iter()
+            .filter_map(|c| canonical_new_file(c))
+            .filter(|(file, _)| is_allowed(file))
+            .min_by_key(|(_, num_missing)| *num_missing)
+            .map(|(file, _)| file);
+        match new {
+            Some(file) if patch.is_new => Ok(file),
+            Some(_) => Err(Error::PatchFileNotFound { path: patch.path.clone() }),
+            None => Err(Error::PatchOutOfBundles { path: patch.path.clone() }),
+        }
+    }
+}
+
+impl UndoFile {
+    /// True if the file content is not the patched one anymore (or deleted).
+    fn is_changed(&self) -> bool {
+        let Some(patched_hash) = self.patched_hash.as_deref() else {
+            return false;
+        };
+        fs::read(&self.path).map_or(true, |content| hash_content(&content) != patched_hash)
+    }
+}
+
+/// Returns the canonical path of the not existing `file`, from its nearest
+/// existing dir, and its number of missing dirs (`None` if the missing part
+/// is not plain names, e.g., `..`).
+fn canonical_new_file(file: &Path) -> Option<(PathBuf, usize)> {
+    let existing = file.ancestors().skip(1).find(|dir| dir.exists())?;
+    if !existing.is_dir() {
+        return None;
+    }
+    let missing = file.strip_prefix(existing).ok()?;
+    if !missing.components().all(|c| matches!(c, Component::Normal(_))) {
+        return None;
+    }
+
+    let num_missing = missing.components().count() - 1;
+
+    Some((existing.canonicalize().ok()?.join(missing), num_missing))
+}
+
+/// Returns the not existing dirs of the `file` path (deepest first).
+fn missing_dirs(file: &Path) -> Vec<PathBuf> {
+    file.ancestors()
+        .skip(1)
+        .take_while(|dir| !dir.exists())
+        .map(Path::to_path_buf)
+        .collect()
+}
+
+/// Returns the colored preview of the `hunk`.
+pub fn fmt_hunk(hunk: &Hunk) -> String {
+    let mut lines = vec![style(&hunk.header).cyan().to_string()];
+    lines.extend(hunk.lines.iter().map(|line| match line {
+        HunkLine::Context(l) => style(format!(" {l}")).dim().to_string(),
+        HunkLine::Removed(l) => style(format!("-{l}")).red().to_string(),
+        HunkLine::Added(l) => style(format!("+{l}")).green().to_string(),
+    }));
+
+    lines.join("\n")
+}
+
+// endregion: --- Patcher
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Result<T> = core::result::Result<T, Error>; // For tests.
+
+    use super::*;
+    use crate::test_support::write_src_project;
+    use tempfile::TempDir;
+
+    const FX_DIFF: &str = "--- a/src/main.rs\n+++ b/src/main.rs\n@@ -1,2 +1,2 @@\n-fn main() {}\n+fn main() { run() }\n // end\n@@ -3 +3,2 @@\n fn run() {}\n+// added\n--- /dev/null\n+++ b/src/new.rs\n@@ -0,0 +1 @@\n+pub fn new() {}\n--- a/Cargo.toml\n+++ b/Cargo.toml\n@@ -1 +1 @@\n-[package]\n+[workspace]\n";
+
+    fn fx_project() -> Result<(TempDir, Patcher)> {
+        let root = TempDir::new()?;
+        write_src_project(root.path(), &[
+            ("src/main.rs", "fn main() {}\n// end\nfn run() {}\n"),
+            ("Cargo.toml", "[package]\n"),
+        ])?;
+        let config_dir = root.path().join("rusty_ai");
+        let patcher = Patcher::new(
+            &[config_dir.join("../src")],
+            &config_dir,
+            config_dir.join("last_patch.json"),
+        );
+
+        Ok((root, patcher))
+    }
+
+    #[test]
+    fn test_patcher_apply_undo_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let (root, patcher) = fx_project()?;
+        let main_file = root.path().join("src/main.rs");
+
+        // -- Exec
+        // Accept all, but the second hunk of main.rs.
+        let mut num_hunk = 0;
+        let patched = patcher.apply(FX_DIFF, &mut |_, _| {
+            num_hunk += 1;
+            Ok(num_hunk != 2)
+        })?;
+
+        // -- Check
+        assert_eq!(patched.len(), 2, "Cargo.toml is out of src_dir");
+        assert_eq!(fs::read_to_string(&main_file)?, "fn main() { run() }\n// end\nfn run() {}\n");
+        assert_eq!(fs::read_to_string(root.path().join("src/new.rs"))?, "pub fn new() {}\n");
+        assert_eq!(fs::read_to_string(root.path().join("Cargo.toml"))?, "[package]\n");
+
+        // -- Exec & Check undo
+        let restored = patcher.undo(&mut |_| Ok(false))?;
+        assert_eq!(restored.len(), 2);
+        assert_eq!(fs::read_to_string(&main_file)?, "fn main() {}\n// end\nfn run() {}\n");
+        assert!(!root.path().join("src/new.rs").exists());
+        assert!(patcher.undo(&mut |_| Ok(false))?.is_empty(), "only the last patch is undone");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_patcher_undo_changed_file_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let (root, patcher) = fx_project()?;
+        let main_file = root.path().join("src/main.rs");
+        patcher.apply(FX_DIFF, &mut |_, _| Ok(true))?;
+        fs::write(&main_file, "fn main() { edited() }\n")?;
+
+        // -- Exec
+        let mut asked = Vec::new();
+        let declined = patcher.undo(&mut |files| {
+            asked = files.to_vec();
+            Ok(false)
+        });
+        let after_decline = fs::read_to_string(&main_file)?;
+        let new_kept = root.path().join("src/new.rs").exists();
+        let confirmed = patcher.undo(&mut |_| Ok(true))?;
+
+        // -- Check
+        assert_eq!(asked, [main_file.canonicalize()?], "only the edited file");
+        assert!(matches!(declined, Err(crate::Error::UndoFilesChanged { .. })), "{declined:?}");
+        assert_eq!(after_decline, "fn main() { edited() }\n", "nothing restored when declined");
+        assert!(new_kept, "nothing restored when declined");
+        assert_eq!(confirmed.len(), 2);
+        assert!(!root.path().join("src/new.rs").exists());
+        assert_eq!(fs::read_to_string(&main_file)?, "fn main() {}\n// end\nfn run() {}\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_patcher_apply_two_blocks_same_file_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let (root, patcher) = fx_project()?;
+        let main_file = root.path().join("src/main.rs");
+        let fx_answer = "First:\n```diff\n--- a/src/main.rs\n+++ b/src/main.rs\n@@ -1 +1 @@\n-fn main() {}\n+fn main() { run() }\n```\nThen:\n```diff\n--- a/src/main.rs\n+++ b/src/main.rs\n@@ -3 +3,2 @@\n fn run() {}\n+// added\n@@ -5 +5 @@\n-fn nope() {}\n+fn yes() {}\n```\n";
+        let diff = extract_diffs(fx_answer).ok_or("no diff")?;
+
+        // -- Exec
+        let mut proposed = Vec::new();
+        let patched = patcher.apply(&diff, &mut |_, hunk| {
+            proposed.push(hunk.header.clone());
+            Ok(true)
+        })?;
+
+        // -- Check
+        assert_eq!(proposed, ["@@ -1 +1 @@", "@@ -3 +3,2 @@"], "the not matching hunk is not proposed");
+        assert_eq!(patched.len(), 1);
+        assert_eq!(fs::read_to_string(&main_file)?, "fn main() { run() }\n// end\nfn run() {}\n// added\n");
+
+        // -- Exec & Check undo
+        assert_eq!(patcher.undo(&mut |_| Ok(false))?.len(), 1);
+        assert_eq!(fs::read_to_string(&main_file)?, "fn main() {}\n// end\nfn run() {}\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_patcher_apply_undo_new_dirs_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let (root, patcher) = fx_project()?;
+        let fx_diff = "--- /dev/null\n+++ b/src/utils/net/mod.rs\n@@ -0,0 +1 @@\n+pub mod http;\n--- /dev/null\n+++ b/src/utils/net/http.rs\n@@ -0,0 +1 @@\n+pub fn get() {}\n--- /dev/null\n+++ b/src/../../out/evil.rs\n@@ -0,0 +1 @@\n+pub fn evil() {}\n";
+
+        // -- Exec
+        let patched = patcher.apply(fx_diff, &mut |_, _| Ok(true))?;
+
+        // -- Check
+        assert_eq!(patched.len(), 2, "the file out of src_dir is skipped");
+        assert_eq!(fs::read_to_string(root.path().join("src/utils/net/http.rs"))?, "pub fn get() {}\n");
+        assert!(!root.path().join("../out").exists());
+
+        // -- Exec & Check undo
+        assert_eq!(patcher.undo(&mut |_| Ok(false))?.len(), 2);
+        assert!(!root.path().join("src/utils").exists(), "created dirs removed");
+        assert!(root.path().join("src/main.rs").is_file());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_patcher_apply_none_accepted_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let (root, patcher) = fx_project()?;
+
+        // -- Exec
+        let patched = patcher.apply(FX_DIFF, &mut |_, _| Ok(false))?;
+
+        // -- Check
+        assert!(patched.is_empty());
+        assert!(!root.path().join("src/new.rs").exists());
+        assert!(patcher.undo(&mut |_| Ok(false))?.is_empty());
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
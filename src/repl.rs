@@ -0,0 +1,633 @@
+// region:    --- Modules
+
+use std::fs::{self, OpenOptions};
+use std::future::Future;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+
+use crate::patch::{extract_diffs, fmt_hunk};
+use crate::rusty_ai::{self, BundleWatch, Conv, RustyAI};
+use crate::tools::{CargoCmd, CargoReport};
+use crate::utils::cli::{confirm, txt_res, ico_check, ico_res, ico_err, fmt_time};
+use crate::utils::editor::compose_message;
+use crate::utils::files::file_io;
+use crate::utils::line_editor::LineEditor;
+use crate::utils::md::{code_blocks, fmt_blocks};
+use crate::utils::render::Renderer;
+use crate::{Error, FileOp, Result};
+
+// endregion: --- Modules
+
+// region:    --- Types
+
+#[derive(Debug)]
+enum Cmd {
+    Quit,
+    Chat(String),
+    /// Composes the message in the editor (with the last answer quoted if true).
+    Edit(bool),
+    RefreshAll,
+    RefreshConv,
+    RefreshInst,
+    RefreshFiles,
+    Search(String),
+    Conv(ConvCmd),
+    History(Option<usize>),
+    Watch(bool),
+    Export(String),
+    Check(CargoCmd),
+    Apply,
+    Undo,
+    Block(BlockCmd),
+    Gc,
+    Help,
+}
+
+#[derive(Debug)]
+enum ConvCmd {
+    List,
+    New(String),
+    Switch(String),
+    Rename(String),
+    Delete(String),
+}
+
+/// On the code blocks of the last answer (numbered from 1).
+#[derive(Debug)]
+enum BlockCmd {
+    List,
+    Write(usize, String),
+    Append(usize, String),
+    Pipe(usize, String),
+}
+
+impl  Cmd {
+    fn from_input(intput: impl Into<String>) -> Self {
+        let input = intput.into();
+
+        if input == ":q" {
+            Self::Quit
+        } else if input == ":e" {
+            Self::Edit(false)
+        } else if input == ":e quote" {
+            Self::Edit(true)
+        } else if input == ":ra" || input == ":RA" {
+            Self::RefreshAll
+        } else if input == ":ri" || input == ":RI" {
+            Self::RefreshInst
+        } else if input == ":rf" || input == ":RF" {
+            Self::RefreshFiles
+        } else if input == ":rc" || input == ":RC" {
+            Self::RefreshConv
+        } else if let Some(query) = input.strip_prefix(":search ") {
+            Self::Search(query.trim().to_string())
+        } else if input == ":convs" {
+            Self::Conv(ConvCmd::List)
+        } else if let Some(args) = input.strip_prefix(":conv ") {
+            ConvCmd::from_args(args).map(Self::Conv).unwrap_or(Self::Help)
+        } else if input == ":history" {
+            Self::History(None)
+        } else if let Some(num) = input.strip_prefix(":history ") {
+            num.trim().parse().map(|n| Self::History(Some(n))).unwrap_or(Self::Help)
+        } else if let Some(file) = input.strip_prefix(":export ") {
+            Self::Export(file.trim().to_string())
+        } else if input == ":watch on" {
+            Self::Watch(true)
+        } else if input == ":watch off" {
+            Self::Watch(false)
+        } else if input == ":check" {
+            Self::Check(CargoCmd::Check)
+        } else if input == ":check test" {
+            Self::Check(CargoCmd::Test)
+        } else if input == ":check clippy" {
+            Self::Check(CargoCmd::Clippy)
+        } else if input == ":apply" {
+            Self::Apply
+        } else if input == ":undo" {
+            Self::Undo
+        } else if input == ":blocks" {
+            Self::Block(BlockCmd::List)
+        } else if let Some(block_cmd) = BlockCmd::from_input(&input) {
+            Self::Block(block_cmd)
+        } else if input == ":gc" {
+            Self::Gc
+        } else if input == ":h" || input == ":H" {
+            Self::Help
+        } else {
+            Self::Chat(input)
+        }
+    }
+}
+
+impl ConvCmd {
+    fn from_args(args: &str) -> Option<Self> {
+        let args: Vec<&str> = args.split_whitespace().collect();
+        let cmd = match args.as_slice() {
+            ["list"] => Self::List,
+            ["new", name] => Self::New(name.to_string()),
+            ["switch", name] => Self::Switch(name.to_string()),
+            ["rename", new_name] => Self::Rename(new_name.to_string()),
+            ["delete", name] => Self::Delete(name.to_string()),
+            _ => return None,
+        };
+
+        Some(cmd)
+    }
+}
+
+impl BlockCmd {
+    /// `:write|:append|:pipe <n> <arg>` (None if not a block command).
+    fn from_input(input: &str) -> Option<Self> {
+        let (cmd, args) = input.split_once(' ')?;
+        let (num, arg) = args.trim().split_once(' ')?;
+        let num = num.parse().ok()?;
+        let arg = arg.trim().to_string();
+
+        match cmd {
+            ":write" => Some(Self::Write(num, arg)),
+            ":append" => Some(Self::Append(num, arg)),
+            ":pipe" => Some(Self::Pipe(num, arg)),
+            _ => None,
+        }
+    }
+}
+
+// endregion: --- Types
+
+const HELP: &[(&str, &str)] = &[
+    (":e [quote]", "compose the message in $EDITOR (quote: the last answer quoted)"),
+    (":ra", "refresh all"),
+    (":ri", "refresh instructions"),
+    (":rf", "refresh files"),
+    (":rc", "refresh converstion"),
+    (":search <query>", "search the files"),
+    (":convs", "list the conversations"),
+    (":conv new <name>", "create and switch to a conversation"),
+    (":conv switch <name>", "switch to a conversation"),
+    (":conv rename <new_name>", "rename the current conversation"),
+    (":conv delete <name>", "delete a conversation"),
+    (":history [n]", "show the conversation (last n messages)"),
+    (":export <file>", "export the conversation (.md or .json)"),
+    (":watch on|off", "re-upload the bundles on source changes"),
+    (":check [test|clippy]", "run cargo, have the diagnostics explained"),
+    (":apply", "apply the diffs of the last answer (hunk by hunk)"),
+    (":undo", "revert the last applied patch"),
+    (":blocks", "list the code blocks of the last answer"),
+    (":write <n> <file>", "write the code block n to a file"),
+    (":append <n> <file>", "append the code block n to a file"),
+    (":pipe <n> <command>", "pipe the code block n to a shell command"),
+    (":gc", "delete the orphan uploaded files"),
+    (":h", "help"),
+    (":q", "quit"),
+];
+
+const SEARCH_NUM_HITS: usize = 5;
+
+/// Returns the tab completions of the prompt, from the `HELP` commands
+/// (e.g., `:conv new <name>` completed as `:conv new `).
+fn completions() -> Vec<String> {
+    let mut completions = Vec::new();
+
+    for (cmd, _) in HELP {
+        let words: Vec<&str> = cmd.split_whitespace().collect();
+        let num_fixed = words.iter().take_while(|w| !w.starts_with(['<', '['])).count();
+        let fixed = words[..num_fixed].join(" ");
+
+        match words.get(num_fixed) {
+            // e.g., `:watch on|off`
+            None => match fixed.split_once(' ') {
+                Some((head, alts)) if alts.contains('|') => {
+                    completions.extend(alts.split('|').map(|alt| format!("{head} {alt}")));
+                }
+                _ => completions.push(fixed),
+            },
+            Some(arg) if arg.starts_with('<') => completions.push(format!("{fixed} ")),
+            // Optional, e.g., `[n]` or `[test|clippy]`
+            Some(arg) => {
+                let opt = arg.trim_matches(['[', ']']);
+                if opt.contains('|') {
+                    completions.extend(opt.split('|').map(|o| format!("{fixed} {o}")));
+                }
+                completions.push(fixed);
+            }
+        }
+    }
+
+    completions
+}
+
+// region:    --- Repl
+
+/// The interactive prompt session.
+pub struct Repl {
+    dir: PathBuf,
+    rusty_ai: RustyAI,
+    conv: Conv,
+    /// Note: stops watching when dropped.
+    bundle_watch: Option<BundleWatch>,
+    renderer: Renderer,
+    line_editor: LineEditor,
+}
+
+impl Repl {
+    pub async fn start(
+        dir: &Path,
+        conv_name: Option<&str>,
+        watch: bool,
+        renderer: Renderer,
+    ) -> Result<Self> {
+        let rusty_ai = RustyAI::init_from_dir(dir, false).await?;
+        let conv = match rusty_ai.load_or_create_conv(conv_name, false).await {
+            Ok(conv) => conv,
+            Err(err) if err.is_fatal() => return Err(err),
+            Err(err) => recover_conv(&rusty_ai, conv_name, err).await?,
+        };
+        let bundle_watch = if watch { Some(rusty_ai.watch()?) } else { None };
+        let line_editor = LineEditor::new(rusty_ai.prompt_history_file()?, completions())?;
+
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            rusty_ai,
+            conv,
+            bundle_watch,
+            renderer,
+            line_editor,
+        })
+    }
+
+    /// Runs the prompt loop until `:q`.
+    ///
+    /// Command errors are printed and the session goes on (with a retry offer
+    /// for a failed chat message). Only fatal errors (see `Error::is_fatal`)
+    /// end the session.
+    /// - Ctrl-C during a chat cancels the run, Ctrl-C (or Ctrl-D) at the prompt quits.
+    pub async fn run(mut self) -> Result<()> {
+        loop {
+            println!();
+            let Some(input) = self.line_editor.read("rusty-ai query")? else {
+                break;
+            };
+            if input.trim().is_empty() {
+                continue;
+            }
+            // Note: the edits and checks end as chat messages, so retried as
+            //       is (not edited, or checked again).
+            let cmd = match Cmd::from_input(input) {
+                Cmd::Quit => break,
+                Cmd::Edit(quote) => self.compose(quote).await.map(|msg| msg.map(Cmd::Chat)),
+                Cmd::Check(cargo_cmd) => self.check(cargo_cmd).await.map(|msg| msg.map(Cmd::Chat)),
+                cmd => Ok(Some(cmd)),
+            };
+            let cmd = match cmd {
+                Ok(Some(cmd)) => cmd,
+                Ok(None) => continue,
+                Err(err) if err.is_fatal() => return Err(err),
+                Err(err) => {
+                    println!("{} {err}", ico_err());
+                    continue;
+                }
+            };
+
+            let mut res = self.exec_interruptible(&cmd).await;
+            while let Err(err) = res {
+                if err.is_fatal() {
+                    return Err(err);
+                }
+                println!("{} {err}", ico_err());
+                let retry_msg = match &cmd {
+                    Cmd::Chat(msg) if !matches!(err, Error::Interrupted) => msg,
+                    _ => break,
+                };
+                if !confirm("Retry the last message?")? {
+                    break;
+                }
+                res = self.chat(retry_msg, true).await;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Execs the `cmd`, stopped by Ctrl-C (`Error::CmdInterrupted`).
+    /// Note: the chats listen to Ctrl-C themselves (to cancel their run).
+    async fn exec_interruptible(&mut self, cmd: &Cmd) -> Result<()> {
+        if matches!(cmd, Cmd::Chat(_)) {
+            self.exec(cmd).await
+        } else {
+            interruptible(self.exec(cmd)).await
+        }
+    }
+
+    async fn exec(&mut self, cmd: &Cmd) -> Result<()> {
+        let rusty_ai = &self.rusty_ai;
+
+        match cmd {
+            Cmd::Quit | Cmd::Edit(_) | Cmd::Check(_) => (),
+            Cmd::Chat(msg) => self.chat(msg, false).await?,
+            Cmd::RefreshAll => {
+                // Note: the current assistant is only deleted once the new one is
+                //       ready, so kept (with the conversation) if the re-init fails.
+                self.rusty_ai = RustyAI::init_from_dir(&self.dir, true).await?;
+                self.conv = self.rusty_ai.recreate_conv(&self.conv).await?;
+                if self.bundle_watch.is_some() {
+                    self.bundle_watch = Some(self.rusty_ai.watch()?);
+                }
+            },
+            Cmd::RefreshConv => {
+                self.conv = rusty_ai.recreate_conv(&self.conv).await?;
+            },
+            Cmd::RefreshInst => {
+                rusty_ai.upload_instructions().await?;
+                self.conv = rusty_ai.recreate_conv(&self.conv).await?;
+            },
+            Cmd::RefreshFiles => {
+                rusty_ai.upload_files(true).await?;
+                self.conv = rusty_ai.recreate_conv(&self.conv).await?;
+            },
+            Cmd::Search(query) => {
+                let hits = rusty_ai.search(query, SEARCH_NUM_HITS)?;
+                if hits.is_empty() {
+                    println!("{} No match", ico_res());
+                }
+                for hit in hits {
+                    println!(
+                        "{} {}:{} ({:.2})",
+                        ico_res(), hit.chunk.path, hit.chunk.start_line, hit.score
+                    );
+                    for (line_num, line) in hit.chunk.snippet(query, 3) {
+                        println!("  {line_num:>5} | {}", txt_res(line.to_string()));
+                    }
+                }
+            },
+            Cmd::Conv(conv_cmd) => match conv_cmd {
+                ConvCmd::List => {
+                    for c in rusty_ai.list_convs()? {
+                        let current = if c.name() == self.conv.name() { "*" } else { " " };
+                        println!(
+                            "{} {current} {}  {}  (created {}, last used {})",
+                            ico_res(),
+                            c.name(),
+                            txt_res(c.title().unwrap_or("-").to_string()),
+                            fmt_time(c.created_at()),
+                            fmt_time(c.last_used())
+                        );
+                    }
+                }
+                ConvCmd::New(name) => self.conv = rusty_ai.create_conv(name).await?,
+                ConvCmd::Switch(name) => self.conv = rusty_ai.switch_conv(name).await?,
+                ConvCmd::Rename(new_name) => rusty_ai.rename_conv(&mut self.conv, new_name)?,
+                ConvCmd::Delete(name) => {
+                    if name == self.conv.name() {
+                        println!("{} Cannot delete the current conversation", ico_err());
+                    } else {
+                        rusty_ai.delete_conv(name).await?;
+                    }
+                }
+            },
+            Cmd::History(num) => {
+                let msgs = rusty_ai.history(&self.conv).await?;
+                let start = num.map_or(0, |n| msgs.len().saturating_sub(n));
+                if msgs.is_empty() {
+                    println!("{} No message yet", ico_res());
+                }
+                for msg in &msgs[start..] {
+                    println!("\n{} {} - {}", ico_res(), msg.role, fmt_time(msg.created_at));
+                    println!("{}", self.renderer.render(&msg.content));
+                }
+            },
+            Cmd::Export(file) => rusty_ai.export_conv(&self.conv, Path::new(file)).await?,
+            Cmd::Watch(on) => {
+                self.bundle_watch = None;
+                if *on {
+                    self.bundle_watch = Some(rusty_ai.watch()?);
+                } else {
+                    println!("{} Watch off", ico_res());
+                }
+            },
+            Cmd::Apply => {
+                let answer = self.last_answer().await?;
+                let diff = extract_diffs(&answer).ok_or(Error::NoDiff)?;
+
+                let patched = rusty_ai.patcher()?.apply(&diff, &mut |file, hunk| {
+                    println!("\n{} {}\n{}", ico_res(), file.display(), fmt_hunk(hunk));
+                    confirm("Apply this hunk?")
+                })?;
+                if patched.is_empty() {
+                    println!("{} Nothing applied", ico_res());
+                } else {
+                    println!("{} {} file(s) patched (:undo to revert)", ico_res(), patched.len());
+                }
+            },
+            Cmd::Undo => {
+                let restored = rusty_ai.patcher()?.undo(&mut |files| {
+                    for file in files {
+                        println!("{} '{}' changed since the patch", ico_err(), file.display());
+                    }
+                    confirm("Undo anyway (these changes lost)?")
+                })?;
+                if restored.is_empty() {
+                    println!("{} No patch to undo", ico_res());
+                }
+            },
+            Cmd::Block(block_cmd) => {
+                let blocks = code_blocks(&self.last_answer().await?);
+                if blocks.is_empty() {
+                    return Err(Error::NoCodeBlock);
+                }
+                let block = |num: usize| {
+                    num.checked_sub(1)
+                        .and_then(|idx| blocks.get(idx))
+                        .ok_or(Error::CodeBlockNotFound { num, count: blocks.len() })
+                };
+
+                match block_cmd {
+                    BlockCmd::List => println!("{} {}", ico_res(), fmt_blocks(&blocks)),
+                    BlockCmd::Write(num, file) => {
+                        let content = &block(*num)?.content;
+                        fs::write(file, content).map_err(file_io(FileOp::Write, Path::new(file)))?;
+                        println!("{} Block {num} written to '{file}'", ico_check());
+                    }
+                    BlockCmd::Append(num, file) => {
+                        let content = &block(*num)?.content;
+                        OpenOptions::new()
+                            .create(true)
+                            .append(true)
+                            .open(file)
+                            .and_then(|mut file_out| file_out.write_all(content.as_bytes()))
+                            .map_err(file_io(FileOp::Write, Path::new(file)))?;
+                        println!("{} Block {num} appended to '{file}'", ico_check());
+                    }
+                    BlockCmd::Pipe(num, command) => pipe_to_shell(&block(*num)?.content, command)?,
+                }
+            },
+            // Note: asks for the confirmation (no `yes` at the prompt).
+            Cmd::Gc => rusty_ai::gc(&self.dir, false).await?,
+            Cmd::Help => {
+                for (cmd, desc) in HELP {
+                    println!("{} {cmd:<24} - {desc}", ico_res());
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Sends the chat `msg`, or runs it again after its failed run if `retry`.
+    async fn chat(&mut self, msg: &str, retry: bool) -> Result<()> {
+        let rusty_ai = &self.rusty_ai;
+
+        let answer = if rusty_ai.stream() {
+            print!("{} ", ico_res());
+            let mut lines = self.renderer.lines();
+            let mut on_delta = |delta: &str| {
+                print!("{}", lines.push(delta));
+                let _ = io::stdout().flush();
+            };
+            let (answer, interrupted) = if retry {
+                rusty_ai.retry_chat_stream(&mut self.conv, msg, &mut on_delta).await?
+            } else {
+                rusty_ai.chat_stream(&mut self.conv, msg, &mut on_delta).await?
+            };
+            println!("{}", lines.finish());
+            if interrupted {
+                println!("{} Interrupted", ico_err());
+            }
+            answer
+        } else {
+            let answer = if retry {
+                rusty_ai.retry_chat(&mut self.conv, msg).await?
+            } else {
+                rusty_ai.chat(&mut self.conv, msg).await?
+            };
+            println!("{} {}", ico_res(), self.renderer.render(&answer));
+            answer
+        };
+
+        let blocks = code_blocks(&answer);
+        if !blocks.is_empty() {
+            println!("{} Code blocks: {} (see :write, :append, :pipe)", ico_res(), fmt_blocks(&blocks));
+        }
+
+        Ok(())
+    }
+
+    /// Returns the message composed in the editor (`None` if empty).
+    async fn compose(&self, quote: bool) -> Result<Option<String>> {
+        let answer = if quote { Some(self.last_answer().await?) } else { None };
+        let msg = compose_message(answer.as_deref())?;
+        if msg.is_none() {
+            println!("{} Empty message, nothing sent", ico_res());
+        }
+
+        Ok(msg)
+    }
+
+    /// Runs the `cargo_cmd`, and returns the message asking to explain its
+    /// diagnostics (`None` if ok without any).
+    async fn check(&self, cargo_cmd: CargoCmd) -> Result<Option<String>> {
+        println!("{} Running cargo {cargo_cmd}...", ico_res());
+        let report = interruptible(self.rusty_ai.cargo(cargo_cmd)).await?;
+        if report.success && report.diagnostics.is_empty() {
+            println!("{} cargo {cargo_cmd} ok, nothing to explain", ico_res());
+            return Ok(None);
+        }
+
+        let timed_out = if report.timed_out { " (timed out)" } else { "" };
+        println!(
+            "{} cargo {cargo_cmd}: {} error(s), {} warning(s){timed_out}",
+            ico_res(), report.errors, report.warnings
+        );
+
+        check_msg(&report).map(Some)
+    }
+
+    /// Returns the last assistant message of the conversation.
+    async fn last_answer(&self) -> Result<String> {
+        let msgs = self.rusty_ai.history(&self.conv).await?;
+        let answer = msgs
+            .into_iter()
+            .rev()
+            .find(|msg| msg.role == "assistant")
+            .ok_or(Error::NoAnswer)?;
+
+        Ok(answer.content)
+    }
+}
+
+/// Offers to load the conversation again after its load `err` (or to start it
+/// again when its thread is gone), instead of ending the session.
+/// Returns the last error if declined.
+async fn recover_conv(rusty_ai: &RustyAI, conv_name: Option<&str>, mut err: Error) -> Result<Conv> {
+    loop {
+        println!("{} {err}", ico_err());
+        let recreate = matches!(err, Error::ThreadNotFound { .. });
+        let question = if recreate {
+            "Start the conversation again (new thread)?"
+        } else {
+            "Retry loading the conversation?"
+        };
+        if !confirm(question)? {
+            return Err(err);
+        }
+
+        err = match rusty_ai.load_or_create_conv(conv_name, recreate).await {
+            Ok(conv) => return Ok(conv),
+            Err(err) if err.is_fatal() => return Err(err),
+            Err(err) => err,
+        };
+    }
+}
+
+/// Returns the `fut` result, or `Error::CmdInterrupted` on Ctrl-C.
+/// Note: once listened to (e.g., by a chat), Ctrl-C does not end the process
+///       anymore, so all the long commands listen to it.
+async fn interruptible<T>(fut: impl Future<Output = Result<T>>) -> Result<T> {
+    tokio::select! {
+        res = fut => res,
+        _ = tokio::signal::ctrl_c() => Err(Error::CmdInterrupted),
+    }
+}
+
+/// Runs the shell `command` with `content` as stdin (output to the terminal).
+fn pipe_to_shell(content: &str, command: &str) -> Result<()> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.arg("/C");
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.arg("-c");
+        shell
+    };
+    let mut child = shell.arg(command).stdin(Stdio::piped()).spawn()?;
+
+    // Note: stdin dropped (closed) once written.
+    let write_res = match child.stdin.take() {
+        Some(mut stdin) => match stdin.write_all(content.as_bytes()) {
+            // The command may exit without reading all its input (e.g., `head`).
+            Err(ex) if ex.kind() == io::ErrorKind::BrokenPipe => Ok(()),
+            res => res.map_err(Error::from),
+        },
+        None => Err(Error::PipeStdin { command: command.to_string() }),
+    };
+    // Note: always waited, so no zombie process is left on a write error.
+    let status = child.wait()?;
+    write_res?;
+    if !status.success() {
+        println!("{} `{command}` failed ({status})", ico_err());
+    }
+
+    Ok(())
+}
+
+/// Returns the chat message asking to explain the cargo `report`.
+fn check_msg(report: &CargoReport) -> Result<String> {
+    Ok(format!(
+        "Explain these `cargo {}` diagnostics and how to fix them:\n\n```json\n{}\n```",
+        report.command,
+        serde_json::to_string_pretty(report)?
+    ))
+}
+
+// endregion: --- Repl
@@ -0,0 +1,204 @@
+//! BM25 full-text index over the bundle chunks.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Result;
+use crate::retrieval::{chunk_bundle, tokenize, Chunk};
+use crate::utils::files::{load_from_json, read_to_string, save_to_json};
+
+// region:    --- Constants
+
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+
+// endregion: --- Constants
+
+// region:    --- Types
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Index {
+    docs: Vec<Doc>,
+    /// term -> number of docs containing it
+    doc_freqs: HashMap<String, usize>,
+    avg_doc_len: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Doc {
+    chunk: Chunk,
+    /// term -> occurrences in the chunk (path included)
+    term_freqs: HashMap<String, usize>,
+    len: usize,
+}
+
+#[derive(Debug)]
+pub struct Hit {
+    pub chunk: Chunk,
+    pub score: f64,
+}
+
+// endregion: --- Types
+
+// region:    --- Build & Persist
+
+impl Index {
+    /// Builds the index from the bundle files.
+    pub fn build(bundle_files: &[impl AsRef<Path>]) -> Result<Self> {
+        let mut index = Index::default();
+
+        for file in bundle_files {
+            let content = read_to_string(file.as_ref())?;
+            for chunk in chunk_bundle(&content) {
+                index.add(chunk);
+            }
+        }
+
+        let total_len: usize = index.docs.iter().map(|d| d.len).sum();
+        index.avg_doc_len = total_len as f64 / index.docs.len().max(1) as f64;
+
+        Ok(index)
+    }
+
+    pub fn load(file: impl AsRef<Path>) -> Result<Self> {
+        load_from_json(file)
+    }
+
+    pub fn save(&self, file: impl AsRef<Path>) -> Result<()> {
+        save_to_json(file, self)
+    }
+
+    pub fn num_chunks(&self) -> usize {
+        self.docs.len()
+    }
+
+    fn add(&mut self, chunk: Chunk) {
+        let mut term_freqs: HashMap<String, usize> = HashMap::new();
+        let mut len = 0;
+        for term in tokenize(&chunk.path).chain(tokenize(&chunk.content)) {
+            *term_freqs.entry(term).or_insert(0) += 1;
+            len += 1;
+        }
+        for term in term_freqs.keys() {
+            *self.doc_freqs.entry(term.clone()).or_insert(0) += 1;
+        }
+
+        self.docs.push(Doc {
+            chunk,
+            term_freqs,
+            len,
+        });
+    }
+}
+
+// endregion: --- Build & Persist
+
+// region:    --- Search
+
+impl Index {
+    /// Returns the `limit` best matching chunks for `query`, best first.
+    pub fn search(&self, query: &str, limit: usize) -> Vec<Hit> {
+        let mut terms: Vec<String> = tokenize(query).collect();
+        terms.sort();
+        terms.dedup();
+
+        let num_docs = self.docs.len() as f64;
+        let mut hits: Vec<(&Doc, f64)> = self
+            .docs
+            .iter()
+            .filter_map(|doc| {
+                let score: f64 = terms
+                    .iter()
+                    .filter_map(|term| {
+                        let tf = *doc.term_freqs.get(term)? as f64;
+                        let df = self.doc_freqs[term] as f64;
+                        let idf = ((num_docs - df + 0.5) / (df + 0.5) + 1.).ln();
+                        let norm = 1. - BM25_B + BM25_B * doc.len as f64 / self.avg_doc_len;
+                        Some(idf * tf * (BM25_K1 + 1.) / (tf + BM25_K1 * norm))
+                    })
+                    .sum();
+                (score > 0.).then_some((doc, score))
+            })
+            .collect();
+
+        hits.sort_by(|a, b| b.1.total_cmp(&a.1));
+
+        hits.into_iter()
+            .take(limit)
+            .map(|(doc, score)| Hit {
+                chunk: doc.chunk.clone(),
+                score,
+            })
+            .collect()
+    }
+}
+
+// endregion: --- Search
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Result<T> = core::result::Result<T, Error>; // For tests.
+
+    use super::*;
+    use crate::retrieval::tests::BUNDLE;
+
+    #[test]
+    fn test_index_search_save_load_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let dir = tempfile::tempdir()?;
+        let bundle_file = dir.path().join("bundle.txt");
+        let index_file = dir.path().join("index.json");
+        std::fs::write(&bundle_file, BUNDLE)?;
+
+        // -- Exec
+        Index::build(&[&bundle_file])?.save(&index_file)?;
+        let index = Index::load(&index_file)?;
+        let hits = index.search("traits behavior", 2);
+
+        // -- Check
+        assert_eq!(index.num_chunks(), 5);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].chunk.path, "files/notes.md");
+        assert_eq!(hits[0].chunk.start_line, 5);
+        assert!(index.search("nothing matches", 5).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_search_marker_in_source_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let dir = tempfile::tempdir()?;
+        let bundle_file = dir.path().join("bundle.txt");
+        let bundle = "
+// ==== file path: src/context.rs
+
+/// Formats the injected context hits.
+fn format_hits() {
+    let header = format!(\"\\n==== file path: {} (from line {})\\n\", path, line);
+    let footer = \"end of the hits\";
+}
+";
+        std::fs::write(&bundle_file, bundle)?;
+
+        // -- Exec
+        let index = Index::build(&[&bundle_file])?;
+        let hits = index.search("footer hits", 5);
+
+        // -- Check
+        assert_eq!(index.num_chunks(), 1);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].chunk.path, "src/context.rs");
+        assert_eq!(hits[0].chunk.start_line, 1);
+        assert!(hits[0].chunk.content.contains("let footer"));
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
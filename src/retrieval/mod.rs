@@ -0,0 +1,309 @@
+//! Local retrieval over the bundle files generated by `bundle_to_file`.
+//!
+//! Bundles are split in chunks per `// ==== file path:` marker line, then per
+//! function/item (rust) or heading (markdown), and chunks are ranked with BM25
+//! (see `Index`).
+
+// region:    --- Modules
+
+use serde::{Deserialize, Serialize};
+
+pub use self::index::{Hit, Index};
+
+mod index;
+
+// endregion: --- Modules
+
+// region:    --- Constants
+
+/// Start of the section marker lines written by `bundle_to_file`
+/// (only at the line start, sources can mention it).
+const FILE_PATH_MARKER: &str = "// ==== file path: ";
+const CHUNK_MAX_LINES: usize = 60;
+
+/// Rust item keywords starting a new chunk (after optional `pub`/`async`...).
+const RUST_ITEM_KEYWORDS: &[&str] = &[
+    "fn", "impl", "struct", "enum", "trait", "mod", "type", "const", "static",
+];
+
+// endregion: --- Constants
+
+// region:    --- Types
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chunk {
+    /// The source file path (from the bundle `==== file path:` marker).
+    pub path: String,
+    /// 1-based line in the source file.
+    pub start_line: usize,
+    pub content: String,
+}
+
+impl Chunk {
+    /// Returns up to `max_lines` `(line_num, line)` of the chunk matching a
+    /// `query` term (first lines if none match).
+    pub fn snippet(&self, query: &str, max_lines: usize) -> Vec<(usize, &str)> {
+        let terms: Vec<String> = tokenize(query).collect();
+        let lines = self.content.lines().enumerate();
+        let line_num = |idx: usize| self.start_line + idx;
+
+        let matching: Vec<(usize, &str)> = lines
+            .clone()
+            .filter(|(_, line)| tokenize(line).any(|t| terms.contains(&t)))
+            .take(max_lines)
+            .map(|(idx, line)| (line_num(idx), line))
+            .collect();
+
+        if matching.is_empty() {
+            lines.take(max_lines).map(|(idx, line)| (line_num(idx), line)).collect()
+        } else {
+            matching
+        }
+    }
+}
+
+// endregion: --- Types
+
+// region:    --- Chunking
+
+/// Splits a bundle file content in chunks (per source file section, then per
+/// rust item or markdown heading, long ones split in `CHUNK_MAX_LINES` windows).
+pub fn chunk_bundle(bundle_content: &str) -> Vec<Chunk> {
+    let mut chunks = Vec::new();
+    let mut path: Option<String> = None;
+    let mut lines: Vec<&str> = Vec::new();
+
+    for line in bundle_content.lines() {
+        if let Some(marker_path) = line.strip_prefix(FILE_PATH_MARKER) {
+            if let Some(path) = path.take() {
+                push_section_chunks(&mut chunks, &path, &lines);
+            }
+            path = Some(marker_path.trim().to_string());
+            lines.clear();
+        } else if path.is_some() {
+            lines.push(line);
+        }
+    }
+    if let Some(path) = path {
+        push_section_chunks(&mut chunks, &path, &lines);
+    }
+
+    chunks
+}
+
+fn push_section_chunks(chunks: &mut Vec<Chunk>, path: &str, lines: &[&str]) {
+    // Note: bundle_to_file adds one empty line after the marker.
+    let lines = lines.strip_prefix(&[""]).unwrap_or(lines);
+
+    let is_md = path.ends_with(".md");
+    let is_boundary = |line: &str| {
+        if is_md {
+            line.starts_with('#')
+        } else {
+            is_rust_item_start(line)
+        }
+    };
+
+    // -- Split at the boundaries (doc comments and attributes stay with the item)
+    let mut starts = vec![0];
+    for (idx, line) in lines.iter().enumerate().skip(1) {
+        if is_boundary(line) {
+            let mut start = idx;
+            while start > 0 && is_item_prefix(lines[start - 1]) {
+                start -= 1;
+            }
+            if start > *starts.last().unwrap_or(&0) {
+                starts.push(start);
+            }
+        }
+    }
+    starts.push(lines.len());
+
+    // -- Create the chunks
+    for range in starts.windows(2) {
+        let (start, end) = (range[0], range[1]);
+        for (idx, window) in lines[start..end].chunks(CHUNK_MAX_LINES).enumerate() {
+            let content = window.join("\n");
+            if content.trim().is_empty() {
+                continue;
+            }
+            chunks.push(Chunk {
+                path: path.to_string(),
+                start_line: start + idx * CHUNK_MAX_LINES + 1,
+                content: content.trim_end().to_string(),
+            });
+        }
+    }
+}
+
+/// Top level (or impl level) rust item start, e.g., `pub async fn name(`.
+fn is_rust_item_start(line: &str) -> bool {
+    let indent = line.len() - line.trim_start().len();
+    if indent > 4 {
+        return false;
+    }
+
+    let mut words = line.split_whitespace().peekable();
+    while let Some(&word) = words.peek() {
+        if word.starts_with("pub") || word == "async" || word == "unsafe" {
+            words.next();
+        } else {
+            break;
+        }
+    }
+
+    // e.g., `impl<T>` is `impl`
+    words
+        .next()
+        .and_then(|w| w.split(|c: char| !c.is_alphanumeric()).next())
+        .is_some_and(|w| RUST_ITEM_KEYWORDS.contains(&w))
+}
+
+fn is_item_prefix(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with("///") || line.starts_with("#[")
+}
+
+// endregion: --- Chunking
+
+// region:    --- Ranking
+
+/// Returns the chunks of the `index` most relevant to `query`,
+/// best first, within `max_chars` of total content.
+pub fn top_chunks(index: &Index, query: &str, max_chars: usize) -> Vec<Chunk> {
+    let mut res = Vec::new();
+    let mut total_chars = 0;
+    for hit in index.search(query, usize::MAX) {
+        if total_chars + hit.chunk.content.len() > max_chars {
+            continue;
+        }
+        total_chars += hit.chunk.content.len();
+        res.push(hit.chunk);
+    }
+
+    res
+}
+
+/// Lowercase alphanumeric terms (2 chars min), `snake_case` split in words.
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.len() > 1)
+        .map(str::to_lowercase)
+}
+
+// endregion: --- Ranking
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Result<T> = core::result::Result<T, Error>; // For tests.
+
+    use super::*;
+
+    pub(super) const BUNDLE: &str = "
+// ==== file path: src/main.rs
+
+fn main() {
+    println!(\"hello\");
+}
+
+/// Say hi.
+#[allow(unused)]
+pub async fn say_hi() {}
+
+
+// ==== file path: src/error.rs
+
+pub type Error = Box<dyn std::error::Error>;
+
+
+// ==== file path: files/notes.md
+
+# Notes
+
+Intro.
+
+## Traits
+
+Traits define shared behavior.
+
+";
+
+    #[test]
+    fn test_chunk_bundle_items_and_headings_ok() -> Result<()> {
+        // -- Exec
+        let chunks = chunk_bundle(BUNDLE);
+
+        // -- Check
+        let summary: Vec<(&str, usize)> = chunks
+            .iter()
+            .map(|c| (c.path.as_str(), c.start_line))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                ("src/main.rs", 1),
+                ("src/main.rs", 5),
+                ("src/error.rs", 1),
+                ("files/notes.md", 1),
+                ("files/notes.md", 5),
+            ]
+        );
+        assert!(chunks[1].content.starts_with("/// Say hi."));
+        assert!(chunks[4].content.starts_with("## Traits"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chunk_bundle_marker_in_source_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let bundle = "
+// ==== file path: src/retrieval/mod.rs
+
+const FILE_PATH_MARKER: &str = \"// ==== file path: \";
+
+fn chunk_bundle() {
+    let marker = \"==== file path:\";
+}
+";
+
+        // -- Exec
+        let chunks = chunk_bundle(bundle);
+
+        // -- Check
+        let summary: Vec<(&str, usize)> = chunks
+            .iter()
+            .map(|c| (c.path.as_str(), c.start_line))
+            .collect();
+        assert_eq!(
+            summary,
+            [("src/retrieval/mod.rs", 1), ("src/retrieval/mod.rs", 3)]
+        );
+        assert!(chunks[0].content.contains("const FILE_PATH_MARKER"));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_top_chunks_rank_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("bundle.txt");
+        std::fs::write(&file, BUNDLE)?;
+
+        // -- Exec
+        let index = Index::build(&[&file])?;
+        let chunks = top_chunks(&index, "What is the Error type?", 10_000);
+
+        // -- Check
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].path, "src/error.rs");
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
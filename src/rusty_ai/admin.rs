@@ -0,0 +1,169 @@
+//! Administration of the assistants and files of the account (`assistants`
+//! and `gc` commands).
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::Result;
+use crate::ais::backend::{new_backend, AiBackend, AsstInfo, FileInfo};
+use crate::utils::cli::{confirm, fmt_bytes, fmt_time, ico_deleted_ok, ico_res};
+use crate::utils::files::load_from_toml;
+
+use super::config::Config;
+use super::{DATA_DIR, RUSTY_AI_TOML};
+
+#[derive(Debug)]
+pub struct AsstAdmin {
+    backend: Box<dyn AiBackend>,
+    /// The project config (its assistant and bundles).
+    config: Config,
+}
+
+impl AsstAdmin {
+    /// Creates the backend of the `dir` config, without loading or creating
+    /// its assistant.
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let config: Config = load_from_toml(dir.join(RUSTY_AI_TOML))?;
+        let backend = new_backend((&config).into(), &dir.join(DATA_DIR))?;
+
+        Ok(Self { backend, config })
+    }
+
+    /// Returns all the assistants (newest first).
+    pub async fn list(&self) -> Result<Vec<AsstInfo>> {
+        self.backend.list_assts().await
+    }
+
+    /// Returns the assistants with the id or name `name_or_id` (newest first).
+    pub async fn find(&self, name_or_id: &str) -> Result<Vec<AsstInfo>> {
+        let assts = self.list().await?;
+
+        Ok(assts
+            .into_iter()
+            .filter(|a| {
+                a.id.as_str() == name_or_id || a.name.as_deref() == Some(name_or_id)
+            })
+            .collect())
+    }
+
+    /// Returns the older duplicates (same name) of the assistants, that is
+    /// all but the newest one (the one loaded by rusty-ai) of each name.
+    /// - `name` to only return the duplicates with this name.
+    pub async fn stale(&self, name: Option<&str>) -> Result<Vec<AsstInfo>> {
+        let mut seen_names = HashSet::new();
+        let stale = self
+            .list()
+            .await?
+            .into_iter()
+            .filter(|a| match (name, a.name.as_deref()) {
+                (_, None) => false,
+                (Some(name), Some(asst_name)) => name == asst_name,
+                (None, Some(_)) => true,
+            })
+            // Note: newest first, so the first one of each name is kept.
+            .filter(|a| !seen_names.insert(a.name.clone()))
+            .collect();
+
+        Ok(stale)
+    }
+
+    /// Returns the uploaded files attached to no assistant.
+    ///
+    /// Note: a file named like a current bundle but detached is an orphan, and
+    ///       the files of the project assistant which are not current bundles
+    ///       (e.g., of a renamed or removed bundle) are orphans too.
+    pub async fn orphan_files(&self) -> Result<Vec<FileInfo>> {
+        let assts = self.list().await?;
+
+        // -- Current bundle names (of the newest project assistant, the loaded one)
+        let project_asst = assts.iter().find(|a| a.name.as_deref() == Some(self.config.name.as_str()));
+        let bundle_names: HashSet<String> = project_asst
+            .map(|asst| {
+                self.config
+                    .file_bundles
+                    .iter()
+                    .map(|bundle| bundle.file_name(&self.config.name, &asst.id))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        // -- Referenced file ids
+        let mut asst_file_ids = HashSet::new();
+        for asst in assts.iter() {
+            let is_project = project_asst.is_some_and(|p| p.id.as_str() == asst.id.as_str());
+            let file_ids = self.backend.get_files_hashmap(&asst.id).await?;
+            asst_file_ids.extend(
+                file_ids
+                    .into_iter()
+                    .filter(|(name, _)| !is_project || bundle_names.contains(name))
+                    .map(|(_, id)| id.to_string()),
+            );
+        }
+
+        // -- The others are orphans
+        let orphans = self
+            .backend
+            .list_files()
+            .await?
+            .into_iter()
+            .filter(|f| !asst_file_ids.contains(f.id.as_str()))
+            .collect();
+
+        Ok(orphans)
+    }
+
+    /// Deletes the assistants (and their files).
+    pub async fn delete_assts(&self, assts: &[AsstInfo]) -> Result<()> {
+        for asst in assts {
+            self.backend.delete_asst(&asst.id).await?;
+        }
+
+        Ok(())
+    }
+
+    pub async fn delete_files(&self, files: &[FileInfo]) -> Result<()> {
+        for file in files {
+            self.backend.delete_file(&file.id).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Lists the orphan uploaded files of the `dir` config, and deletes them
+/// after confirmation (or directly if `yes`). The `gc` command and `:gc`.
+pub async fn gc(dir: &Path, yes: bool) -> Result<()> {
+    let admin = AsstAdmin::from_dir(dir)?;
+    let orphans = admin.orphan_files().await?;
+
+    if orphans.is_empty() {
+        println!("{} No orphan file", ico_res());
+        return Ok(());
+    }
+
+    let mut total_bytes = 0;
+    for file in orphans.iter() {
+        total_bytes += file.bytes;
+        println!(
+            "{} {}  {}  {}  ({})",
+            ico_res(),
+            file.id,
+            file.name,
+            fmt_bytes(file.bytes),
+            fmt_time(file.created_at)
+        );
+    }
+
+    let question = format!(
+        "Delete these {} orphan files ({})?",
+        orphans.len(),
+        fmt_bytes(total_bytes)
+    );
+    if yes || confirm(&question)? {
+        admin.delete_files(&orphans).await?;
+        println!("{} {} files deleted", ico_deleted_ok(), orphans.len());
+    }
+
+    Ok(())
+}
@@ -1,12 +1,41 @@
 use serde::Deserialize;
 
-use crate::ais::asst;
+use crate::ais::asst::{AsstId, PollPolicy};
+use crate::ais::backend::{BackendConfig, BackendKind};
+use crate::ais::retry::RetryPolicy;
+use crate::tools::{CargoConfig, ToolDef};
 
 #[derive(Debug, Deserialize)]
 pub(super) struct Config {
     pub name: String,
     pub model: String,
     pub instructions_file: String,
+    #[serde(default)]
+    pub backend: BackendKind,
+    /// Custom OpenAI compatible api base (default OpenAI)
+    pub api_base: Option<String>,
+    /// Print the responses as they arrive (default true)
+    #[serde(default = "default_true")]
+    pub stream: bool,
+    /// Number of local search hits appended to each chat message (default 0)
+    #[serde(default)]
+    pub search_inject: usize,
+    /// Max duration of a run in seconds, cancelled after (default 300)
+    #[serde(default = "default_run_timeout_secs")]
+    pub run_timeout_secs: u64,
+    /// Retries of the failed API requests (`[retry]` table)
+    #[serde(default)]
+    pub retry: RetryPolicy,
+    /// Polling of the runs (`[polling]` table)
+    #[serde(default)]
+    pub polling: PollPolicy,
+    /// Local function tools of the assistant (`[[tools]]` tables)
+    /// Note: not supported by the `openai-chat` backend (ignored, with a warning).
+    #[serde(default)]
+    pub tools: Vec<ToolDef>,
+    /// Limits of the cargo tool and `:check` (`[cargo]` table)
+    #[serde(default)]
+    pub cargo: CargoConfig,
     pub file_bundles: Vec<FileBundle>
 }
 
@@ -18,13 +47,31 @@ pub(super) struct FileBundle {
     pub src_globs: Vec<String>,
 }
 
+impl FileBundle {
+    /// The name of the bundle file of the assistant `asst_name` (`asst_id`),
+    /// in `.rusty_ai/files` and once uploaded.
+    pub fn file_name(&self, asst_name: &str, asst_id: &AsstId) -> String {
+        format!("{asst_name}-{}-bundle-{asst_id}.{}", self.bundle_name, self.dst_ext)
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_run_timeout_secs() -> u64 {
+    300
+}
+
 // region:    --- Forms
 
-impl From<&Config> for asst::CreateConfig {
+impl From<&Config> for BackendConfig {
     fn from(config: &Config) -> Self {
         Self {
-            name: config.name.clone(),
-            model: config.model.clone(),
+            kind: config.backend,
+            api_base: config.api_base.clone(),
+            retry: config.retry.clone(),
+            poll: config.polling.clone(),
         }
     }
 }
@@ -0,0 +1,103 @@
+use std::path::Path;
+
+use chrono::Utc;
+use derive_more::Deref;
+use serde::{Deserialize, Serialize};
+
+use crate::{Error, Result};
+use crate::ais::asst::ThreadId;
+use crate::utils::files::load_from_json;
+
+// region:    --- Constants
+
+pub(super) const DEFAULT_CONV_NAME: &str = "default";
+const TITLE_MAX_CHARS: usize = 60;
+
+// endregion: --- Constants
+
+/// A named conversation, stored in `.rusty_ai/convs/{name}.json`.
+#[derive(Debug, Deref, Deserialize, Serialize)]
+pub struct Conv {
+    /// The file stem (not serialized)
+    #[serde(skip)]
+    pub(super) name: String,
+    #[deref]
+    pub(super) thread_id: ThreadId,
+    /// First line of the first message (if any)
+    #[serde(default)]
+    pub(super) title: Option<String>,
+    /// Unix timestamp (seconds)
+    #[serde(default)]
+    pub(super) created_at: i64,
+    /// Unix timestamp (seconds)
+    #[serde(default)]
+    pub(super) last_used: i64,
+}
+
+impl Conv {
+    pub(super) fn new(name: String, thread_id: ThreadId) -> Self {
+        let now = Utc::now().timestamp();
+        Self {
+            name,
+            thread_id,
+            title: None,
+            created_at: now,
+            last_used: now,
+        }
+    }
+
+    /// Loads the conversation `name` from its `file` (`None` if not found,
+    /// `Error::ConvInvalid` if it cannot be parsed).
+    pub(super) fn load(file: &Path, name: &str) -> Result<Option<Self>> {
+        match load_from_json::<Conv>(file) {
+            Ok(mut conv) => {
+                conv.name = name.to_string();
+                Ok(Some(conv))
+            }
+            Err(Error::FileNotFound(_)) => Ok(None),
+            Err(Error::Json(err)) => Err(Error::ConvInvalid {
+                name: name.to_string(),
+                cause: err.to_string(),
+            }),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn title(&self) -> Option<&str> {
+        self.title.as_deref()
+    }
+
+    pub fn created_at(&self) -> i64 {
+        self.created_at
+    }
+
+    pub fn last_used(&self) -> i64 {
+        self.last_used
+    }
+
+    /// Updates the last used time, and the title from the first `msg`.
+    pub(super) fn touch(&mut self, msg: &str) {
+        self.last_used = Utc::now().timestamp();
+        if self.title.is_none() {
+            let first_line = msg.lines().find(|l| !l.trim().is_empty()).unwrap_or("");
+            let title: String = first_line.trim().chars().take(TITLE_MAX_CHARS).collect();
+            self.title = Some(title).filter(|t| !t.is_empty());
+        }
+    }
+}
+
+/// Conversation names are used as file names (letters, digits, `-` and `_`).
+pub(super) fn validate_conv_name(name: &str) -> Result<()> {
+    let is_valid = !name.is_empty()
+        && name.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_');
+
+    if is_valid {
+        Ok(())
+    } else {
+        Err(Error::InvalidConvName { name: name.to_string() })
+    }
+}
@@ -0,0 +1,43 @@
+//! Conversation history export (Markdown and JSON).
+
+use serde::Serialize;
+
+use crate::Result;
+use crate::ais::backend::ThreadMsg;
+use crate::utils::cli::fmt_time;
+
+use super::Conv;
+
+#[derive(Serialize)]
+struct JsonExport<'a> {
+    name: &'a str,
+    title: Option<&'a str>,
+    thread_id: &'a str,
+    messages: &'a [ThreadMsg],
+}
+
+pub(super) fn to_markdown(conv: &Conv, msgs: &[ThreadMsg]) -> String {
+    let mut md = format!("# {}\n", conv.title().unwrap_or(conv.name()));
+
+    for msg in msgs {
+        md.push_str(&format!(
+            "\n## {} - {}\n\n{}\n",
+            msg.role,
+            fmt_time(msg.created_at),
+            msg.content.trim_end()
+        ));
+    }
+
+    md
+}
+
+pub(super) fn to_json(conv: &Conv, msgs: &[ThreadMsg]) -> Result<String> {
+    let export = JsonExport {
+        name: conv.name(),
+        title: conv.title(),
+        thread_id: conv.thread_id.as_str(),
+        messages: msgs,
+    };
+
+    Ok(serde_json::to_string_pretty(&export)?)
+}
@@ -0,0 +1,104 @@
+//! Content hashes of the bundles, to re-upload only the changed ones.
+//!
+//! Stored in `.rusty_ai/manifest.json`.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::files::{file_io, hash_content};
+use crate::{FileOp, Result};
+
+// region:    --- Types
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(super) struct Manifest {
+    /// bundle file name -> bundle entry
+    pub bundles: BTreeMap<String, BundleEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub(super) struct BundleEntry {
+    /// Hash of the bundle file content
+    pub hash: String,
+    /// source file path (relative to the bundle `src_dir`) -> content hash
+    pub files: BTreeMap<String, String>,
+}
+
+// endregion: --- Types
+
+impl BundleEntry {
+    pub fn new(bundle_file: &Path, src_dir: &Path, files: &[PathBuf]) -> Result<Self> {
+        let mut file_hashes = BTreeMap::new();
+        for file in files {
+            let rel_path = file.strip_prefix(src_dir).unwrap_or(file);
+            file_hashes.insert(rel_path.to_string_lossy().to_string(), hash_file(file)?);
+        }
+
+        Ok(Self {
+            hash: hash_file(bundle_file)?,
+            files: file_hashes,
+        })
+    }
+
+    /// Returns the source file changes since `prev`, as `+ added`, `~ modified`,
+    /// and `- removed` paths.
+    pub fn changes(&self, prev: &BundleEntry) -> Vec<String> {
+        let mut changes = Vec::new();
+        for (path, hash) in self.files.iter() {
+            match prev.files.get(path) {
+                None => changes.push(format!("+ {path}")),
+                Some(prev_hash) if prev_hash != hash => changes.push(format!("~ {path}")),
+                Some(_) => (),
+            }
+        }
+        for path in prev.files.keys().filter(|p| !self.files.contains_key(*p)) {
+            changes.push(format!("- {path}"));
+        }
+
+        changes
+    }
+}
+
+fn hash_file(file: &Path) -> Result<String> {
+    Ok(hash_content(&fs::read(file).map_err(file_io(FileOp::Read, file))?))
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Result<T> = core::result::Result<T, Error>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_bundle_entry_changes_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let dir = tempfile::tempdir()?;
+        let src_dir = dir.path();
+        let bundle_file = src_dir.join("bundle.rs");
+        for (name, content) in [("a.rs", "a"), ("b.rs", "b"), ("c.rs", "c"), ("bundle.rs", "x")] {
+            fs::write(src_dir.join(name), content)?;
+        }
+        let prev_files = ["a.rs", "b.rs"].map(|f| src_dir.join(f));
+        let prev = BundleEntry::new(&bundle_file, src_dir, &prev_files)?;
+        fs::write(src_dir.join("b.rs"), "b2")?;
+
+        // -- Exec
+        let new_files = ["b.rs", "c.rs"].map(|f| src_dir.join(f));
+        let entry = BundleEntry::new(&bundle_file, src_dir, &new_files)?;
+
+        // -- Check
+        assert_eq!(entry.changes(&prev), ["~ b.rs", "+ c.rs", "- a.rs"]);
+        assert_eq!(entry.hash, prev.hash);
+        assert_eq!(entry.hash.len(), 64);
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
@@ -1,39 +1,62 @@
 // region:    --- Modules
 
+use std::cmp::Reverse;
 use std::fs;
 use std::path::{PathBuf, Path};
-use derive_more::{From, Deref};
-use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
 
-use crate::Result; 
-use crate::ais::{new_oa_client, OaClient};
-use crate::ais::asst::{self, AsstId, ThreadId}; 
+use tokio::sync::Mutex;
+
+use crate::{Error, FileOp, Result};
+use crate::ais::asst::{AsstId, CreateConfig, OnDelta};
+use crate::ais::backend::{new_backend, user_text, AiBackend, BackendKind, RunMsg, ThreadMsg, SEARCH_HITS_MARKER};
+use crate::patch::Patcher;
+use crate::retrieval::{Hit, Index};
+use crate::tools::{run_cargo, CargoCmd, CargoReport, ToolCtx, Tools};
 use crate::utils::files::{self, 
     ensure_dir, load_from_toml, 
     load_from_json, save_to_json, 
-    list_files, bundle_to_file
+    list_files, bundle_to_file, file_io
 };
-use crate::utils::cli::ico_check;
+use crate::utils::cli::{ico_check, ico_deleted_ok, ico_err, ico_uploaded};
+use crate::utils::files::XFile;
+
+use self::config::{Config, FileBundle};
+use self::conv::{validate_conv_name, DEFAULT_CONV_NAME};
+use self::manifest::{BundleEntry, Manifest};
 
-use self::config::Config;
+pub use self::admin::{gc, AsstAdmin};
+pub use self::conv::Conv;
+pub use self::watch::BundleWatch;
 
+mod admin;
 mod config;
+mod conv;
+mod history;
+mod manifest;
+mod watch;
+#[cfg(test)]
+mod tests;
 
 // endregion: --- Modules
 
 const RUSTY_AI_TOML: &str = "rusty_ai.toml";
+pub(crate) const DATA_DIR: &str = ".rusty_ai";
 
-#[derive(Debug)]
+/// Cheap to clone (shared backend), e.g., for the background watch task.
+#[derive(Debug, Clone)]
 pub struct RustyAI {
     dir: PathBuf,
-    oac: OaClient,
+    backend: Arc<dyn AiBackend>,
     asst_id: AsstId,
-    config: Config,
-}
-
-#[derive(Debug, From, Deref, Deserialize, Serialize)]
-pub struct Conv {
-    thread_id: ThreadId,
+    config: Arc<Config>,
+    /// Serializes the bundle uploads (foreground and watch).
+    upload_lock: Arc<Mutex<()>>,
+    /// Max duration of a chat run (`run_timeout_secs` unless overridden).
+    run_timeout: Duration,
+    /// The function tools of the assistant (`[[tools]]`).
+    tools: Arc<Tools>,
 }
 
 /// Public functions
@@ -42,6 +65,15 @@ impl RustyAI {
         &self.config.name
     }
 
+    pub fn stream(&self) -> bool {
+        self.config.stream
+    }
+
+    /// Overrides the `run_timeout_secs` of the config (e.g., `--timeout`).
+    pub fn set_run_timeout(&mut self, timeout: Duration) {
+        self.run_timeout = timeout;
+    }
+
     pub async fn init_from_dir(
         dir: impl AsRef<Path>,
         recreate_asst: bool,
@@ -51,33 +83,18 @@ impl RustyAI {
         // -- Load from the directory
         let config: Config = load_from_toml(dir.join(RUSTY_AI_TOML))?;
 
-        // -- Get or Create the OpenAI Assistant
-        let oac = new_oa_client()?;
-        let asst_id = asst::load_or_create_asst(&oac, (&config).into(), recreate_asst).await?;
+        // -- Create the backend selected in the config
+        let backend = new_backend((&config).into(), &dir.join(DATA_DIR))?;
 
-        // -- Create RustyAI
-        let rusty_ai = RustyAI {
-            dir: dir.to_path_buf(),
-            oac,
-            asst_id,
-            config
-        };
-
-        // -- Upload instructions 
-        rusty_ai.upload_instructions().await?;
-
-        // -- Upload files
-        rusty_ai.upload_files(false).await?;
-
-        Ok(rusty_ai)
+        Self::init(dir, config, backend, recreate_asst).await
     }
 
     pub async fn upload_instructions(&self) -> Result<bool> {
         let file = self.dir.join(&self.config.instructions_file);
         if file.exists() {
             let inst_content = files::read_to_string(&file)?;
-            asst::upload_instructions(&self.oac, &self.asst_id, inst_content).await?;
-            println!("{} Instructions uploaded", ico_check());
+            self.backend.upload_instructions(&self.asst_id, inst_content).await?;
+            eprintln!("{} Instructions uploaded", ico_check());
             Ok(true)
         } else {
             Ok(false)
@@ -85,6 +102,7 @@ impl RustyAI {
     }
 
     pub async fn upload_files(&self, recreate: bool) -> Result<u32> {
+        let _upload_guard = self.upload_lock.lock().await;
         let mut num_uploaded = 0;
 
         // the .rusty_ai/files
@@ -96,82 +114,106 @@ impl RustyAI {
             &data_files_dir, Some(&["*.rs", "*.md"]), 
             Some(&[&exclude_element]),
         )? {
-            let file_str = file.to_string_lossy();
             // Safegaurd
-            if !file_str.contains(".rusty_ai") {
-                return Err(
-                    format!("Error should not delete: '{}'", file_str).into()
-                );
+            if !file.to_string_lossy().contains(".rusty_ai") {
+                return Err(Error::UnsafeDelete(file));
             }
             // delete file
-            fs::remove_file(&file)?;
+            fs::remove_file(&file).map_err(file_io(FileOp::Delete, &file))?;
         }
 
         // --- Generate and upload the .rusty_ai/files bundle files.
+        let mut bundle_file_names = Vec::new();
         for bundle in self.config.file_bundles.iter() {
-            let src_dir = self.dir.join(&bundle.src_dir);
-
-            if src_dir.is_dir() {
-                let src_globs: Vec<&str> = bundle.src_globs
-                    .iter()
-                    .map(AsRef::as_ref)
-                    .collect();
-
-                let files = list_files(&src_dir, Some(&src_globs), None)?;
-
-                if !files.is_empty() {
-                    // Compute bundle file name
-                    let buddle_file_name = format!(
-                        "{}-{}-bundle-{}.{}",
-                        self.name(),
-                        bundle.bundle_name,
-                        self.asst_id,
-                        bundle.dst_ext
-                    );
-
-                    let bundle_file = self.data_files_dir()?.join(buddle_file_name);
-
-                    // If it doesn't exist, then we will force a reupload.
-                    let force_reupload = recreate || !bundle_file.exists();
-
-                    // Rebundle no matter if exist or not (to check).
-                    bundle_to_file(files, &bundle_file)?;
-
-                    // Upload
-                    let (_, uploaded) = asst::upload_file_by_name(
-                        &self.oac,
-                        &self.asst_id,
-                        &bundle_file,
-                        force_reupload,
-                    ).await?;
-
-                    if uploaded {
-                        num_uploaded += 1;
-                    }
+            if let Some((bundle_file, uploaded)) = self.upload_bundle(bundle, recreate).await? {
+                if uploaded {
+                    num_uploaded += 1;
                 }
+                bundle_file_names.push(bundle_file.x_file_name().to_string());
             }
         }
 
+        // -- Only keep the current bundles in the manifest.
+        let manifest_file = self.manifest_file()?;
+        let mut manifest: Manifest = load_from_json(&manifest_file).unwrap_or_default();
+        manifest.bundles.retain(|name, _| bundle_file_names.contains(name));
+        save_to_json(&manifest_file, &manifest)?;
+
+        // -- Keep the local search index in sync with the bundles.
+        self.update_index()?;
+
         Ok(num_uploaded)
     }
 
-    pub async fn load_or_create_conv(&self, recreate: bool) -> Result<Conv> {
-        let conv_file = self.data_dir()?.join("conv.json");
+    /// Runs the cargo `cmd` on the project (same as the `cargo` tool).
+    /// Dropped (e.g., interrupted), cargo is killed.
+    pub async fn cargo(&self, cmd: CargoCmd) -> Result<CargoReport> {
+        let (ctx, _cancel) = self.tools.ctx().for_call();
+        tokio::task::spawn_blocking(move || run_cargo(&ctx, cmd))
+            .await
+            .map_err(|err| Error::TaskFailed { task: format!("cargo {cmd}"), cause: err.to_string() })?
+    }
+
+    /// Returns the patcher of the bundle sources (undo record in `.rusty_ai/`).
+    pub fn patcher(&self) -> Result<Patcher> {
+        let src_dirs: Vec<PathBuf> = self
+            .config
+            .file_bundles
+            .iter()
+            .map(|bundle| self.dir.join(&bundle.src_dir))
+            .collect();
+
+        Ok(Patcher::new(&src_dirs, &self.dir, self.data_dir()?.join("last_patch.json")))
+    }
+
+    /// The history of the interactive prompt inputs (in `.rusty_ai/`).
+    pub fn prompt_history_file(&self) -> Result<PathBuf> {
+        Ok(self.data_dir()?.join("prompt_history.txt"))
+    }
+
+    /// Returns the `limit` best matching bundle chunks from the local index.
+    pub fn search(&self, query: &str, limit: usize) -> Result<Vec<Hit>> {
+        let index = Index::load(self.index_file()?).map_err(|_| Error::NoSearchIndex)?;
+
+        Ok(index.search(query, limit))
+    }
+
+    /// Loads or creates the conversation `name` (the last used one, or
+    /// "default", if `None`).
+    /// - `recreate` to start it again with a new thread.
+    /// - The runs left active on a loaded thread (e.g., killed process) are cancelled.
+    pub async fn load_or_create_conv(
+        &self,
+        name: Option<&str>,
+        recreate: bool,
+    ) -> Result<Conv> {
+        let name = match name {
+            Some(name) => name.to_string(),
+            None => self
+                .list_convs()?
+                .into_iter()
+                .next()
+                .map(|c| c.name)
+                .unwrap_or_else(|| DEFAULT_CONV_NAME.to_string()),
+        };
+        let conv_file = self.conv_file(&name)?;
 
         if recreate && conv_file.exists() {
-            fs::remove_file(&conv_file)?;
+            fs::remove_file(&conv_file).map_err(file_io(FileOp::Delete, &conv_file))?;
         }
 
-        let conv = if let Ok(conv) = load_from_json::<Conv>(&conv_file) {
-            asst::get_thread(&self.oac, &conv.thread_id)
-                .await
-                .map_err(|_| format!("Cannot find thread_id for {:?}", conv))?;
-            println!("{} Conversation loaded", ico_check());
+        let conv = if let Some(conv) = Conv::load(&conv_file, &name)? {
+            self.backend.get_thread(&conv.thread_id).await?;
+            let num_cancelled = self.backend.cancel_active_runs(&conv.thread_id).await?;
+            if num_cancelled > 0 {
+                eprintln!("{} {num_cancelled} dangling run(s) cancelled", ico_deleted_ok());
+            }
+            eprintln!("{} Conversation '{}' loaded", ico_check(), conv.name);
             conv
         } else {
-            let thread_id = asst::create_thred(&self.oac).await?;
-            println!("{} Conversation created", ico_check());
-            let conv = thread_id.into();
+            let thread_id = self.backend.create_thread().await?;
+            let conv = Conv::new(name, thread_id);
+            eprintln!("{} Conversation '{}' created", ico_check(), conv.name);
             save_to_json(&conv_file, &conv)?;
             conv
         };
@@ -179,23 +221,323 @@ impl RustyAI {
         Ok(conv)
     }
 
-    pub async fn chat(&self, conv: &Conv, msg: &str) -> Result<String> {
-        let res = asst::run_thread_msg(
-            &self.oac, 
-            &self.asst_id, 
-            &conv.thread_id, 
-            msg
-        ).await?;
+    /// Starts the conversation again (same name, new thread).
+    pub async fn recreate_conv(&self, conv: &Conv) -> Result<Conv> {
+        self.load_or_create_conv(Some(&conv.name), true).await
+    }
 
-        Ok(res)
+    /// Creates the conversation `name` (error if it already exists).
+    pub async fn create_conv(&self, name: &str) -> Result<Conv> {
+        if self.conv_file(name)?.exists() {
+            return Err(Error::ConvExists { name: name.to_string() });
+        }
+        self.load_or_create_conv(Some(name), false).await
+    }
 
+    /// Loads the existing conversation `name`.
+    pub async fn switch_conv(&self, name: &str) -> Result<Conv> {
+        if !self.conv_file(name)?.exists() {
+            return Err(Error::ConvNotFound { name: name.to_string() });
+        }
+        self.load_or_create_conv(Some(name), false).await
+    }
+
+    /// Returns the conversations, last used first.
+    /// (the unreadable conversation files are reported and skipped)
+    pub fn list_convs(&self) -> Result<Vec<Conv>> {
+        let mut convs = Vec::new();
+        for file in list_files(&self.convs_dir()?, Some(&["*.json"]), None)? {
+            match Conv::load(&file, file.x_file_stem()) {
+                Ok(Some(conv)) => convs.push(conv),
+                Ok(None) => (),
+                Err(err) => eprintln!("{} {err} (skipped)", ico_err()),
+            }
+        }
+        convs.sort_by_key(|c| Reverse(c.last_used));
+
+        Ok(convs)
+    }
+
+    pub fn rename_conv(&self, conv: &mut Conv, new_name: &str) -> Result<()> {
+        let new_file = self.conv_file(new_name)?;
+        if new_file.exists() {
+            return Err(Error::ConvExists { name: new_name.to_string() });
+        }
+
+        let conv_file = self.conv_file(&conv.name)?;
+        fs::rename(&conv_file, new_file).map_err(file_io(FileOp::Rename, &conv_file))?;
+        eprintln!("{} Conversation '{}' renamed '{new_name}'", ico_check(), conv.name);
+        conv.name = new_name.to_string();
+
+        Ok(())
+    }
+
+    /// Deletes the conversation `name` and its thread.
+    pub async fn delete_conv(&self, name: &str) -> Result<()> {
+        let conv_file = self.conv_file(name)?;
+        let conv: Conv = load_from_json(&conv_file)
+            .map_err(|_| Error::ConvNotFound { name: name.to_string() })?;
+
+        // Note: the thread might be already gone, that's ok.
+        let _ = self.backend.delete_thread(&conv.thread_id).await;
+        fs::remove_file(&conv_file).map_err(file_io(FileOp::Delete, &conv_file))?;
+        eprintln!("{} Conversation '{name}' deleted", ico_deleted_ok());
+
+        Ok(())
+    }
+
+    /// Returns the messages of the conversation (oldest first), without the
+    /// appended search hits.
+    pub async fn history(&self, conv: &Conv) -> Result<Vec<ThreadMsg>> {
+        let mut msgs = self.backend.list_thread_msgs(&conv.thread_id).await?;
+        for msg in msgs.iter_mut() {
+            msg.content.truncate(user_text(&msg.content).len());
+        }
+
+        Ok(msgs)
+    }
+
+    /// Exports the conversation history to `file`, as Markdown (`.md`) or
+    /// JSON (`.json`).
+    pub async fn export_conv(&self, conv: &Conv, file: &Path) -> Result<()> {
+        let msgs = self.history(conv).await?;
+        let content = match file.extension().and_then(|e| e.to_str()) {
+            Some("md") => history::to_markdown(conv, &msgs),
+            Some("json") => history::to_json(conv, &msgs)?,
+            _ => return Err(Error::ExportFormat { file: file.to_path_buf() }),
+        };
+        fs::write(file, content).map_err(file_io(FileOp::Write, file))?;
+        eprintln!("{} Conversation '{}' exported to '{}'", ico_check(), conv.name, file.display());
+
+        Ok(())
+    }
+
+    pub async fn chat(&self, conv: &mut Conv, msg: &str) -> Result<String> {
+        self.run_chat(conv, msg, false).await
+    }
+
+    /// Same as `chat`, after a failed run of the same `msg` (not added again
+    /// to the thread if already there).
+    pub async fn retry_chat(&self, conv: &mut Conv, msg: &str) -> Result<String> {
+        self.run_chat(conv, msg, true).await
+    }
+
+    /// Same as `chat`, but calls `on_delta` with the response text as it arrives.
+    ///
+    /// Returns `(response_text, has_been_interrupted)`
+    pub async fn chat_stream(
+        &self,
+        conv: &mut Conv,
+        msg: &str,
+        on_delta: &mut OnDelta<'_>,
+    ) -> Result<(String, bool)> {
+        self.run_chat_stream(conv, msg, false, on_delta).await
+    }
+
+    /// Same as `retry_chat`, but streamed (see `chat_stream`).
+    pub async fn retry_chat_stream(
+        &self,
+        conv: &mut Conv,
+        msg: &str,
+        on_delta: &mut OnDelta<'_>,
+    ) -> Result<(String, bool)> {
+        self.run_chat_stream(conv, msg, true, on_delta).await
     }
 }
 
 /// Private functions 
 impl RustyAI {
+    async fn init(
+        dir: &Path,
+        config: Config,
+        backend: Box<dyn AiBackend>,
+        recreate_asst: bool,
+    ) -> Result<Self> {
+        // -- Load the local tools
+        let src_dirs: Vec<PathBuf> = config.file_bundles.iter().map(|b| dir.join(&b.src_dir)).collect();
+        let tools = Tools::new(&config.tools, ToolCtx {
+            root: files::common_dir(&src_dirs).unwrap_or_else(|| dir.to_path_buf()),
+            config_dir: dir.to_path_buf(),
+            index_file: dir.join(DATA_DIR).join("index").join("index.json"),
+            cargo: config.cargo.clone(),
+            target_dir: dir.join(DATA_DIR).join("target"),
+            cancel: Default::default(),
+        })
+        .map_err(|err| Error::Config {
+            file: dir.join(RUSTY_AI_TOML),
+            cause: err.to_string(),
+        })?;
+
+        if matches!(config.backend, BackendKind::OpenaiChat) && !tools.functions().is_empty() {
+            println!(
+                "{} The [[tools]] of {RUSTY_AI_TOML} are ignored by the openai-chat backend",
+                ico_err()
+            );
+        }
+
+        // -- Get or Create the Assistant
+        let asst_config = CreateConfig {
+            name: config.name.clone(),
+            model: config.model.clone(),
+            functions: tools.functions().to_vec(),
+        };
+        let asst_id = backend
+            .load_or_create_asst(asst_config, recreate_asst)
+            .await?;
+
+        // -- Create RustyAI
+        let rusty_ai = RustyAI {
+            dir: dir.to_path_buf(),
+            backend: backend.into(),
+            asst_id,
+            run_timeout: Duration::from_secs(config.run_timeout_secs),
+            tools: Arc::new(tools),
+            config: Arc::new(config),
+            upload_lock: Arc::default(),
+        };
+
+        // -- Upload instructions 
+        rusty_ai.upload_instructions().await?;
+
+        // -- Upload files
+        rusty_ai.upload_files(false).await?;
+
+        // -- Delete the previous assistant once the new one is ready
+        //    (so still there if the re-init fails)
+        if recreate_asst {
+            rusty_ai.delete_previous_assts().await?;
+        }
+
+        Ok(rusty_ai)
+    }
+
+    /// Deletes the other assistants with the name of the current one.
+    async fn delete_previous_assts(&self) -> Result<()> {
+        for asst in self.backend.list_assts().await? {
+            if asst.name.as_deref() == Some(self.name()) && asst.id.as_str() != self.asst_id.as_str() {
+                self.backend.delete_asst(&asst.id).await?;
+                eprintln!("{} Assistant {} deleted", ico_deleted_ok(), self.name());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Re-bundles `bundle`, and uploads it if its content changed (or `recreate`).
+    ///
+    /// Returns `(bundle_file, has_been_uploaded)` (None if no source files).
+    async fn upload_bundle(
+        &self,
+        bundle: &FileBundle,
+        recreate: bool,
+    ) -> Result<Option<(PathBuf, bool)>> {
+        let src_dir = self.dir.join(&bundle.src_dir);
+        if !src_dir.is_dir() {
+            return Ok(None);
+        }
+
+        let src_globs: Vec<&str> = bundle.src_globs
+            .iter()
+            .map(AsRef::as_ref)
+            .collect();
+        let files = list_files(&src_dir, Some(&src_globs), None)?;
+        if files.is_empty() {
+            return Ok(None);
+        }
+
+        let bundle_file = self.bundle_file(bundle)?;
+        let bundle_file_name = bundle_file.x_file_name().to_string();
+
+        // If it doesn't exist, then we will force a reupload.
+        let mut force_reupload = recreate || !bundle_file.exists();
+
+        // Rebundle no matter if exist or not (to check).
+        let entry = {
+            bundle_to_file(files.clone(), &bundle_file)?;
+            BundleEntry::new(&bundle_file, &src_dir, &files)?
+        };
+
+        // If the content changed, then we will force a reupload.
+        let manifest_file = self.manifest_file()?;
+        let mut manifest: Manifest = load_from_json(&manifest_file).unwrap_or_default();
+        let prev_entry = manifest.bundles.get(&bundle_file_name);
+        let changes = match prev_entry {
+            Some(prev) if prev.hash != entry.hash => {
+                // Note: the bundle can change with the same sources (e.g., its format).
+                let changes = entry.changes(prev);
+                if changes.is_empty() { vec!["(content changed)".to_string()] } else { changes }
+            }
+            Some(_) => Vec::new(),
+            None => vec!["(no previous hash)".to_string()],
+        };
+        force_reupload |= !changes.is_empty();
+
+        // Upload
+        let (_, uploaded) = self.backend.upload_file_by_name(
+            &self.asst_id,
+            &bundle_file,
+            force_reupload,
+        ).await?;
+
+        if uploaded && prev_entry.is_some() && !changes.is_empty() {
+            eprintln!(
+                "{} Bundle '{}' changed: {}",
+                ico_uploaded(),
+                bundle.bundle_name,
+                changes.join(", ")
+            );
+        }
+
+        // Record the uploaded content.
+        manifest.bundles.insert(bundle_file_name, entry);
+        save_to_json(&manifest_file, &manifest)?;
+
+        Ok(Some((bundle_file, uploaded)))
+    }
+
+    /// Re-bundles and re-uploads `bundle` if changed, and updates the index.
+    async fn sync_bundle(&self, bundle: &FileBundle) -> Result<()> {
+        let _upload_guard = self.upload_lock.lock().await;
+        let uploaded = self
+            .upload_bundle(bundle, false)
+            .await?
+            .is_some_and(|(_, uploaded)| uploaded);
+        if uploaded {
+            self.update_index()?;
+        }
+
+        Ok(())
+    }
+
+    /// Rebuilds the local search index from the current bundle files.
+    fn update_index(&self) -> Result<()> {
+        let mut bundle_files = Vec::new();
+        for bundle in self.config.file_bundles.iter() {
+            let bundle_file = self.bundle_file(bundle)?;
+            if bundle_file.is_file() {
+                bundle_files.push(bundle_file);
+            }
+        }
+
+        let index = Index::build(&bundle_files)?;
+        index.save(self.index_file()?)?;
+        eprintln!("{} Index updated ({} chunks)", ico_check(), index.num_chunks());
+
+        Ok(())
+    }
+
+    fn bundle_file(&self, bundle: &FileBundle) -> Result<PathBuf> {
+        let bundle_file_name = bundle.file_name(self.name(), &self.asst_id);
+
+        Ok(self.data_files_dir()?.join(bundle_file_name))
+    }
+
+    fn manifest_file(&self) -> Result<PathBuf> {
+        Ok(self.data_dir()?.join("manifest.json"))
+    }
+
     fn data_dir(&self) -> Result<PathBuf> {
-        let data_dir = self.dir.join(".rusty_ai");
+        let data_dir = self.dir.join(DATA_DIR);
         ensure_dir(&data_dir)?;  
         Ok(data_dir)
     }
@@ -205,4 +547,98 @@ impl RustyAI {
         ensure_dir(&dir)?;  
         Ok(dir)
     }
+
+    /// The `.rusty_ai/convs` dir (with the legacy conv files moved in).
+    fn convs_dir(&self) -> Result<PathBuf> {
+        let data_dir = self.data_dir()?;
+        let dir = data_dir.join("convs");
+        ensure_dir(&dir)?;
+
+        // -- Move the legacy `conv.json` and `conv-{name}.json` files
+        for file in list_files(&data_dir, Some(&["*/conv.json", "*/conv-*.json"]), None)? {
+            let name = file
+                .x_file_stem()
+                .strip_prefix("conv-")
+                .unwrap_or(DEFAULT_CONV_NAME)
+                .to_string();
+            let dst = dir.join(format!("{name}.json"));
+            if !dst.exists() {
+                fs::rename(&file, dst).map_err(file_io(FileOp::Rename, &file))?;
+            }
+        }
+
+        Ok(dir)
+    }
+
+    fn conv_file(&self, name: &str) -> Result<PathBuf> {
+        validate_conv_name(name)?;
+        Ok(self.convs_dir()?.join(format!("{name}.json")))
+    }
+
+    async fn run_chat(&self, conv: &mut Conv, msg: &str, retry: bool) -> Result<String> {
+        self.touch_conv(conv, msg)?;
+        let content = self.with_search_hits(msg)?;
+        let res = self.backend.run_thread_msg(
+            &self.asst_id, 
+            &conv.thread_id, 
+            RunMsg { content: &content, retry },
+            &self.tools,
+            self.run_timeout,
+        ).await?;
+
+        Ok(res)
+    }
+
+    async fn run_chat_stream(
+        &self,
+        conv: &mut Conv,
+        msg: &str,
+        retry: bool,
+        on_delta: &mut OnDelta<'_>,
+    ) -> Result<(String, bool)> {
+        self.touch_conv(conv, msg)?;
+        let content = self.with_search_hits(msg)?;
+        let res = self.backend.run_thread_msg_stream(
+            &self.asst_id,
+            &conv.thread_id,
+            RunMsg { content: &content, retry },
+            &self.tools,
+            self.run_timeout,
+            on_delta,
+        ).await?;
+
+        Ok(res)
+    }
+
+    fn touch_conv(&self, conv: &mut Conv, msg: &str) -> Result<()> {
+        conv.touch(msg);
+        save_to_json(self.conv_file(&conv.name)?, conv)
+    }
+
+    fn index_file(&self) -> Result<PathBuf> {
+        let dir = self.data_dir()?.join("index");
+        ensure_dir(&dir)?;
+        Ok(dir.join("index.json"))
+    }
+
+    /// Appends the top `search_inject` local search hits to `msg` (if any).
+    fn with_search_hits(&self, msg: &str) -> Result<String> {
+        let mut msg = msg.to_string();
+        if self.config.search_inject == 0 {
+            return Ok(msg);
+        }
+
+        let hits = self.search(&msg, self.config.search_inject)?;
+        if !hits.is_empty() {
+            msg.push_str(SEARCH_HITS_MARKER);
+            for hit in hits {
+                msg.push_str(&format!(
+                    "\n==== file path: {} (from line {})\n{}\n",
+                    hit.chunk.path, hit.chunk.start_line, hit.chunk.content
+                ));
+            }
+        }
+
+        Ok(msg)
+    }
 }
\ No newline at end of file
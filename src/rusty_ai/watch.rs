@@ -0,0 +1,107 @@
+//! Watch mode: re-bundles and re-uploads the bundles when their source files
+//! change, in a background task.
+
+use std::collections::{BTreeSet, HashSet};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio::time::timeout;
+
+use crate::Result;
+use crate::utils::cli::{ico_check, ico_err};
+use crate::utils::files::get_glob_set;
+
+use super::RustyAI;
+
+// region:    --- Constants
+
+/// Quiet time after the last change before re-bundling.
+const DEBOUNCE_MS: u64 = 500;
+
+// endregion: --- Constants
+
+/// Watches the bundle source dirs until dropped.
+#[derive(Debug)]
+pub struct BundleWatch {
+    _watcher: RecommendedWatcher,
+    task: JoinHandle<()>,
+}
+
+impl Drop for BundleWatch {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+impl RustyAI {
+    /// Starts watching the `src_dir` of each file bundle. Changed bundles
+    /// (per `src_globs`) are re-bundled and re-uploaded in the background.
+    pub fn watch(&self) -> Result<BundleWatch> {
+        let (tx, mut rx) = mpsc::unbounded_channel::<Vec<PathBuf>>();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            if let Ok(event) = res {
+                if !matches!(event.kind, EventKind::Access(_)) {
+                    let _ = tx.send(event.paths);
+                }
+            }
+        })?;
+
+        // -- Watch the src dirs (canonical, as the event paths)
+        let mut watched = Vec::new();
+        for (idx, bundle) in self.config.file_bundles.iter().enumerate() {
+            let src_dir = self.dir.join(&bundle.src_dir);
+            if !src_dir.is_dir() {
+                continue;
+            }
+            let src_dir = src_dir.canonicalize()?;
+            watcher.watch(&src_dir, RecursiveMode::Recursive)?;
+
+            let src_globs: Vec<&str> = bundle.src_globs.iter().map(AsRef::as_ref).collect();
+            watched.push((idx, src_dir, get_glob_set(&src_globs)?));
+        }
+        // Note: the bundle files must not trigger a new sync.
+        let data_dir = self.data_dir()?.canonicalize()?;
+        eprintln!("{} Watching {} source dirs", ico_check(), watched.len());
+
+        // -- Sync the changed bundles
+        let rusty_ai = self.clone();
+        let task = tokio::spawn(async move {
+            while let Some(paths) = rx.recv().await {
+                let mut changed: HashSet<PathBuf> = paths.into_iter().collect();
+                let debounce = Duration::from_millis(DEBOUNCE_MS);
+                while let Ok(Some(paths)) = timeout(debounce, rx.recv()).await {
+                    changed.extend(paths);
+                }
+                changed.retain(|path| !path.starts_with(&data_dir));
+
+                let bundle_idxs: BTreeSet<usize> = watched
+                    .iter()
+                    .filter(|(_, src_dir, globs)| {
+                        changed.iter().any(|p| p.starts_with(src_dir) && globs.is_match(p))
+                    })
+                    .map(|(idx, ..)| *idx)
+                    .collect();
+
+                for idx in bundle_idxs {
+                    let bundle = &rusty_ai.config.file_bundles[idx];
+                    if let Err(err) = rusty_ai.sync_bundle(bundle).await {
+                        eprintln!(
+                            "{} Cannot sync bundle '{}'\n     cause: {}",
+                            ico_err(),
+                            bundle.bundle_name,
+                            err
+                        );
+                    }
+                }
+            }
+        });
+
+        Ok(BundleWatch {
+            _watcher: watcher,
+            task,
+        })
+    }
+}
@@ -0,0 +1,901 @@
+//! Offline stand-in for the OpenAI Assistants, Files, Threads, Messages and Runs
+//! endpoints used by `ais::asst`.
+//!
+//! Runs go through `queued -> in_progress -> <final>` (one step per retrieve).
+//! The final status is `completed` (assistant answers `echo: <user msg>`),
+//! unless the user message contains `mock:<status>` (e.g., `mock:failed`),
+//! or `mock:queued` for a run staying queued (until cancelled), or
+//! `mock:failed_once` for only the first run of the thread failing (retries).
+//! Like the API, no message can be added while a run of the thread is active.
+//!
+//! A user message `mock:tool:<name> <json args>` makes the run call the function
+//! `name` (`requires_action`), then answer `tool output: <output>` once the tool
+//! outputs are submitted (recorded in `MockRun::tool_outputs`), and
+//! `mock:requires_action` makes it require an action without any tool call.
+//!
+//! Runs created with `"stream": true` respond with all the run events at once
+//! (answer sent as one `thread.message.delta` per word), or none for a user
+//! message containing `mock:stream_stalled` (the run staying `in_progress`).
+//!
+//! Chat completions answer `echo: <last user msg>` (streamed one chunk per word
+//! when `"stream": true`), and are recorded in `MockState::chat_requests`.
+//!
+//! `MockState::faults` are answered (one per request, in order) instead of the
+//! next requests, to test the retries.
+
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use axum::body::{Body, Bytes};
+use axum::extract::{Multipart, Path, Query, Request, State};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde_json::{json, Value};
+use tokio::net::TcpListener;
+
+// region:    --- Types
+
+#[derive(Debug, Clone)]
+pub struct MockFile {
+    pub id: String,
+    pub filename: String,
+    pub content: String,
+}
+
+/// Error response answered instead of a request.
+#[derive(Debug, Clone, Copy)]
+pub enum MockFault {
+    /// 429 with a "try again in <ms>ms" message (and `retry-after-ms` header).
+    RateLimited { retry_after_ms: u64 },
+    /// 500 `server_error`.
+    ServerError,
+    /// 503 with an HTML body, as answered by a gateway or proxy.
+    GatewayHtml,
+    /// 401 `invalid_api_key`.
+    Unauthorized,
+}
+
+#[derive(Debug)]
+pub struct MockRun {
+    pub obj: Value,
+    /// Remaining statuses, popped on each retrieve.
+    pub statuses: Vec<&'static str>,
+    pub answer: String,
+    /// The function call `(name, arguments)` of the run, if any.
+    pub tool_call: Option<(String, String)>,
+    /// The submitted tool outputs.
+    pub tool_outputs: Vec<Value>,
+}
+
+#[derive(Debug, Default)]
+pub struct MockState {
+    next_id: u32,
+    pub assistants: Vec<Value>,
+    /// asst_id -> file_ids
+    pub asst_files: HashMap<String, Vec<String>>,
+    pub files: Vec<MockFile>,
+    pub threads: HashMap<String, Value>,
+    /// thread_id -> messages (oldest first)
+    pub messages: HashMap<String, Vec<Value>>,
+    pub runs: HashMap<String, MockRun>,
+    pub chat_requests: Vec<Value>,
+    pub faults: VecDeque<MockFault>,
+}
+
+type SharedState = Arc<Mutex<MockState>>;
+
+pub struct MockOaServer {
+    api_base: String,
+    state: SharedState,
+}
+
+// endregion: --- Types
+
+// region:    --- Server
+
+impl MockOaServer {
+    /// Starts the server on a random local port.
+    pub async fn start() -> Self {
+        let state = SharedState::default();
+
+        let app = Router::new()
+            .route("/v1/assistants", post(create_asst).get(list_assts))
+            .route(
+                "/v1/assistants/:asst_id",
+                get(get_asst).post(update_asst).delete(delete_asst),
+            )
+            .route(
+                "/v1/assistants/:asst_id/files",
+                post(create_asst_file).get(list_asst_files),
+            )
+            .route(
+                "/v1/assistants/:asst_id/files/:file_id",
+                axum::routing::delete(delete_asst_file),
+            )
+            .route("/v1/files", post(create_file).get(list_files))
+            .route("/v1/files/:file_id", axum::routing::delete(delete_file))
+            .route("/v1/threads", post(create_thread))
+            .route(
+                "/v1/threads/:thread_id",
+                get(get_thread).delete(delete_thread),
+            )
+            .route(
+                "/v1/threads/:thread_id/messages",
+                post(create_msg).get(list_msgs),
+            )
+            .route("/v1/threads/:thread_id/runs", post(create_run).get(list_runs))
+            .route("/v1/threads/:thread_id/runs/:run_id", get(get_run))
+            .route(
+                "/v1/threads/:thread_id/runs/:run_id/cancel",
+                post(cancel_run),
+            )
+            .route(
+                "/v1/threads/:thread_id/runs/:run_id/submit_tool_outputs",
+                post(submit_tool_outputs),
+            )
+            .route("/v1/chat/completions", post(chat_completions))
+            .layer(middleware::from_fn_with_state(state.clone(), inject_fault))
+            .with_state(state.clone());
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await });
+
+        Self {
+            api_base: format!("http://{addr}/v1"),
+            state,
+        }
+    }
+
+    pub fn api_base(&self) -> &str {
+        &self.api_base
+    }
+
+    pub fn state(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap()
+    }
+}
+
+impl MockState {
+    fn new_id(&mut self, prefix: &str) -> String {
+        self.next_id += 1;
+        format!("{prefix}_{:04}", self.next_id)
+    }
+}
+
+async fn inject_fault(State(st): State<SharedState>, req: Request, next: Next) -> Response {
+    let fault = st.lock().unwrap().faults.pop_front();
+
+    match fault {
+        Some(MockFault::RateLimited { retry_after_ms }) => {
+            let body = json!({ "error": {
+                "message": format!("Rate limit reached. Please try again in {retry_after_ms}ms."),
+                "type": "requests",
+                "param": null,
+                "code": "rate_limit_exceeded",
+            }});
+            let headers = [("retry-after-ms", retry_after_ms.to_string())];
+            (StatusCode::TOO_MANY_REQUESTS, headers, Json(body)).into_response()
+        }
+        Some(MockFault::ServerError) => {
+            let body = json!({ "error": {
+                "message": "The server had an error while processing your request.",
+                "type": "server_error",
+                "param": null,
+                "code": null,
+            }});
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(body)).into_response()
+        }
+        Some(MockFault::Unauthorized) => {
+            let body = json!({ "error": {
+                "message": "Incorrect API key provided: sk-mock.",
+                "type": "invalid_request_error",
+                "param": null,
+                "code": "invalid_api_key",
+            }});
+            (StatusCode::UNAUTHORIZED, Json(body)).into_response()
+        }
+        Some(MockFault::GatewayHtml) => {
+            let body = "<html><body><h1>503 Service Temporarily Unavailable</h1></body></html>";
+            (StatusCode::SERVICE_UNAVAILABLE, [("content-type", "text/html")], body).into_response()
+        }
+        None => next.run(req).await,
+    }
+}
+
+fn not_found(what: &str, id: &str) -> Response {
+    let body = json!({
+        "error": {
+            "message": format!("No {what} found with id '{id}'."),
+            "type": "invalid_request_error",
+            "param": null,
+            "code": null,
+        }
+    });
+    (StatusCode::NOT_FOUND, Json(body)).into_response()
+}
+
+fn list_obj(data: Vec<Value>) -> Json<Value> {
+    Json(json!({
+        "object": "list",
+        "first_id": data.first().map(|v| v["id"].clone()),
+        "last_id": data.last().map(|v| v["id"].clone()),
+        "has_more": false,
+        "data": data,
+    }))
+}
+
+/// Returns the `data` page after the `after` query id, of `limit` items.
+fn paged_list_obj(mut data: Vec<Value>, query: &HashMap<String, String>) -> Json<Value> {
+    let limit = query
+        .get("limit")
+        .and_then(|l| l.parse().ok())
+        .unwrap_or(20);
+    if let Some(after) = query.get("after") {
+        let start = data.iter().position(|m| m["id"] == after.as_str()).map_or(0, |i| i + 1);
+        data.drain(..start);
+    }
+    let has_more = data.len() > limit;
+    let mut res = list_obj(data.into_iter().take(limit).collect());
+    res["has_more"] = has_more.into();
+    res
+}
+
+fn deleted_obj(id: &str, object: &str) -> Json<Value> {
+    Json(json!({ "id": id, "object": object, "deleted": true }))
+}
+
+// endregion: --- Server
+
+// region:    --- Assistants
+
+async fn create_asst(State(st): State<SharedState>, Json(req): Json<Value>) -> Response {
+    let mut st = st.lock().unwrap();
+    let id = st.new_id("asst");
+    let obj = json!({
+        "id": id,
+        "object": "assistant",
+        "created_at": 1700000000 + st.next_id,
+        "name": req["name"],
+        "description": null,
+        "model": req["model"],
+        "instructions": req["instructions"],
+        "tools": req.get("tools").cloned().unwrap_or(json!([])),
+        "file_ids": [],
+        "metadata": {},
+    });
+    st.assistants.push(obj.clone());
+    Json(obj).into_response()
+}
+
+async fn list_assts(
+    State(st): State<SharedState>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    let st = st.lock().unwrap();
+    // Default order is newest first (desc).
+    let data = st.assistants.iter().rev().map(|a| asst_with_files(&st, a)).collect();
+    paged_list_obj(data, &query).into_response()
+}
+
+async fn get_asst(State(st): State<SharedState>, Path(asst_id): Path<String>) -> Response {
+    let st = st.lock().unwrap();
+    match st.assistants.iter().find(|a| a["id"] == asst_id) {
+        Some(asst) => Json(asst_with_files(&st, asst)).into_response(),
+        None => not_found("assistant", &asst_id),
+    }
+}
+
+async fn update_asst(
+    State(st): State<SharedState>,
+    Path(asst_id): Path<String>,
+    Json(req): Json<Value>,
+) -> Response {
+    let mut st = st.lock().unwrap();
+    let Some(asst) = st.assistants.iter_mut().find(|a| a["id"] == asst_id) else {
+        return not_found("assistant", &asst_id);
+    };
+    for key in ["instructions", "name", "tools"] {
+        if let Some(val) = req.get(key).filter(|v| !v.is_null()) {
+            asst[key] = val.clone();
+        }
+    }
+    let asst = asst.clone();
+    Json(asst_with_files(&st, &asst)).into_response()
+}
+
+async fn delete_asst(State(st): State<SharedState>, Path(asst_id): Path<String>) -> Response {
+    let mut st = st.lock().unwrap();
+    let len = st.assistants.len();
+    st.assistants.retain(|a| a["id"] != asst_id);
+    if st.assistants.len() == len {
+        return not_found("assistant", &asst_id);
+    }
+    st.asst_files.remove(&asst_id);
+    deleted_obj(&asst_id, "assistant.deleted").into_response()
+}
+
+fn asst_with_files(st: &MockState, asst: &Value) -> Value {
+    let mut asst = asst.clone();
+    let file_ids = asst["id"]
+        .as_str()
+        .and_then(|id| st.asst_files.get(id))
+        .cloned()
+        .unwrap_or_default();
+    asst["file_ids"] = json!(file_ids);
+    asst
+}
+
+// endregion: --- Assistants
+
+// region:    --- Assistant Files
+
+async fn create_asst_file(
+    State(st): State<SharedState>,
+    Path(asst_id): Path<String>,
+    Json(req): Json<Value>,
+) -> Response {
+    let mut st = st.lock().unwrap();
+    let file_id = req["file_id"].as_str().unwrap_or_default().to_string();
+    if !st.files.iter().any(|f| f.id == file_id) {
+        return not_found("file", &file_id);
+    }
+    st.asst_files.entry(asst_id.clone()).or_default().push(file_id.clone());
+    Json(json!({
+        "id": file_id,
+        "object": "assistant.file",
+        "created_at": 1700000000,
+        "assistant_id": asst_id,
+    }))
+    .into_response()
+}
+
+async fn list_asst_files(
+    State(st): State<SharedState>,
+    Path(asst_id): Path<String>,
+) -> Response {
+    let st = st.lock().unwrap();
+    let data = st
+        .asst_files
+        .get(&asst_id)
+        .into_iter()
+        .flatten()
+        .map(|file_id| {
+            json!({
+                "id": file_id,
+                "object": "assistant.file",
+                "created_at": 1700000000,
+                "assistant_id": asst_id,
+            })
+        })
+        .collect();
+    list_obj(data).into_response()
+}
+
+async fn delete_asst_file(
+    State(st): State<SharedState>,
+    Path((asst_id, file_id)): Path<(String, String)>,
+) -> Response {
+    let mut st = st.lock().unwrap();
+    let Some(file_ids) = st.asst_files.get_mut(&asst_id) else {
+        return not_found("assistant", &asst_id);
+    };
+    let len = file_ids.len();
+    file_ids.retain(|id| *id != file_id);
+    if file_ids.len() == len {
+        return not_found("assistant file", &file_id);
+    }
+    deleted_obj(&file_id, "assistant.file.deleted").into_response()
+}
+
+// endregion: --- Assistant Files
+
+// region:    --- Files
+
+async fn create_file(State(st): State<SharedState>, mut multipart: Multipart) -> Response {
+    let mut file = None;
+    while let Ok(Some(field)) = multipart.next_field().await {
+        if field.name() == Some("file") {
+            let filename = field.file_name().unwrap_or_default().to_string();
+            let content = field.text().await.unwrap_or_default();
+            file = Some((filename, content));
+        }
+    }
+    let Some((filename, content)) = file else {
+        return (StatusCode::BAD_REQUEST, "missing file part").into_response();
+    };
+
+    let mut st = st.lock().unwrap();
+    let file = MockFile {
+        id: st.new_id("file"),
+        filename,
+        content,
+    };
+    st.files.push(file.clone());
+    Json(file_obj(&file)).into_response()
+}
+
+async fn list_files(State(st): State<SharedState>) -> Response {
+    let st = st.lock().unwrap();
+    let data: Vec<Value> = st.files.iter().map(file_obj).collect();
+    Json(json!({ "object": "list", "data": data })).into_response()
+}
+
+async fn delete_file(State(st): State<SharedState>, Path(file_id): Path<String>) -> Response {
+    let mut st = st.lock().unwrap();
+    let len = st.files.len();
+    st.files.retain(|f| f.id != file_id);
+    if st.files.len() == len {
+        return not_found("file", &file_id);
+    }
+    deleted_obj(&file_id, "file").into_response()
+}
+
+fn file_obj(file: &MockFile) -> Value {
+    json!({
+        "id": file.id,
+        "object": "file",
+        "bytes": file.content.len(),
+        "created_at": 1700000000,
+        "filename": file.filename,
+        "purpose": "assistants",
+        "status": "processed",
+        "status_details": null,
+    })
+}
+
+// endregion: --- Files
+
+// region:    --- Threads & Messages
+
+async fn create_thread(State(st): State<SharedState>) -> Response {
+    let mut st = st.lock().unwrap();
+    let id = st.new_id("thread");
+    let obj = json!({
+        "id": id,
+        "object": "thread",
+        "created_at": 1700000000,
+        "metadata": {},
+    });
+    st.threads.insert(id.clone(), obj.clone());
+    st.messages.insert(id, Vec::new());
+    Json(obj).into_response()
+}
+
+async fn get_thread(
+    State(st): State<SharedState>,
+    Path(thread_id): Path<String>,
+) -> Response {
+    let st = st.lock().unwrap();
+    match st.threads.get(&thread_id) {
+        Some(thread) => Json(thread.clone()).into_response(),
+        None => not_found("thread", &thread_id),
+    }
+}
+
+async fn delete_thread(
+    State(st): State<SharedState>,
+    Path(thread_id): Path<String>,
+) -> Response {
+    let mut st = st.lock().unwrap();
+    if st.threads.remove(&thread_id).is_none() {
+        return not_found("thread", &thread_id);
+    }
+    st.messages.remove(&thread_id);
+    deleted_obj(&thread_id, "thread.deleted").into_response()
+}
+
+async fn create_msg(
+    State(st): State<SharedState>,
+    Path(thread_id): Path<String>,
+    Json(req): Json<Value>,
+) -> Response {
+    let mut st = st.lock().unwrap();
+    if !st.threads.contains_key(&thread_id) {
+        return not_found("thread", &thread_id);
+    }
+    let active_run = st.runs.values().find(|r| {
+        r.obj["thread_id"] == thread_id.as_str()
+            && ["queued", "in_progress", "requires_action", "cancelling"]
+                .contains(&r.obj["status"].as_str().unwrap_or_default())
+    });
+    if let Some(run) = active_run {
+        let body = json!({ "error": {
+            "message": format!("Can't add messages to {thread_id} while a run {} is active.", run.obj["id"]),
+            "type": "invalid_request_error",
+            "param": null,
+            "code": null,
+        }});
+        return (StatusCode::BAD_REQUEST, Json(body)).into_response();
+    }
+    let content = req["content"].as_str().unwrap_or_default();
+    let msg = new_msg(&mut st, &thread_id, "user", content, None);
+    Json(msg).into_response()
+}
+
+async fn list_msgs(
+    State(st): State<SharedState>,
+    Path(thread_id): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+) -> Response {
+    let st = st.lock().unwrap();
+    let Some(msgs) = st.messages.get(&thread_id) else {
+        return not_found("thread", &thread_id);
+    };
+
+    // Default order is newest first (desc).
+    let mut msgs = msgs.clone();
+    if query.get("order").map(String::as_str) != Some("asc") {
+        msgs.reverse();
+    }
+    paged_list_obj(msgs, &query).into_response()
+}
+
+fn new_msg(
+    st: &mut MockState,
+    thread_id: &str,
+    role: &str,
+    content: &str,
+    run: Option<(&str, &str)>,
+) -> Value {
+    let id = st.new_id("msg");
+    let (asst_id, run_id) = run.unzip();
+    let msg = json!({
+        "id": id,
+        "object": "thread.message",
+        "created_at": 1700000000 + st.next_id,
+        "thread_id": thread_id,
+        "role": role,
+        "content": [{
+            "type": "text",
+            "text": { "value": content, "annotations": [] }
+        }],
+        "assistant_id": asst_id,
+        "run_id": run_id,
+        "file_ids": [],
+        "metadata": {},
+    });
+    st.messages.entry(thread_id.to_string()).or_default().push(msg.clone());
+    msg
+}
+
+// endregion: --- Threads & Messages
+
+// region:    --- Runs
+
+async fn create_run(
+    State(st): State<SharedState>,
+    Path(thread_id): Path<String>,
+    Json(req): Json<Value>,
+) -> Response {
+    let mut st = st.lock().unwrap();
+    let Some(msgs) = st.messages.get(&thread_id) else {
+        return not_found("thread", &thread_id);
+    };
+    let last_user_msg = msgs
+        .iter()
+        .rev()
+        .find(|m| m["role"] == "user")
+        .and_then(|m| m["content"][0]["text"]["value"].as_str())
+        .unwrap_or_default()
+        .to_string();
+
+    let tool_call = last_user_msg.split("mock:tool:").nth(1).map(|call| {
+        let call = call.lines().next().unwrap_or_default();
+        let (name, args) = call.split_once(' ').unwrap_or((call, "{}"));
+        (name.to_string(), args.trim().to_string())
+    });
+    let first_run = !st.runs.values().any(|r| r.obj["thread_id"] == thread_id.as_str());
+    let failed_once = last_user_msg.contains("mock:failed_once");
+    let final_status = ["failed", "cancelled", "expired", "queued", "requires_action"]
+        .into_iter()
+        .find(|s| last_user_msg.contains(&format!("mock:{s}")))
+        .filter(|_| !failed_once || first_run)
+        .unwrap_or(if tool_call.is_some() { "requires_action" } else { "completed" });
+
+    let id = st.new_id("run");
+    let mut obj = json!({
+        "id": id,
+        "object": "thread.run",
+        "created_at": 1700000000,
+        "thread_id": thread_id,
+        "assistant_id": req["assistant_id"],
+        "status": "queued",
+        "required_action": null,
+        "last_error": null,
+        "expires_at": null,
+        "started_at": null,
+        "cancelled_at": null,
+        "failed_at": null,
+        "completed_at": null,
+        "model": "mock-model",
+        "instructions": "",
+        "tools": [],
+        "file_ids": [],
+        "metadata": {},
+    });
+    let answer = format!("echo: {last_user_msg}");
+
+    if final_status == "requires_action" {
+        obj["required_action"] = required_action(&id, tool_call.as_ref());
+    }
+
+    if req["stream"] == true && last_user_msg.contains("mock:stream_stalled") {
+        obj["status"] = json!("in_progress");
+        let run = MockRun {
+            obj,
+            statuses: Vec::new(),
+            answer,
+            tool_call,
+            tool_outputs: Vec::new(),
+        };
+        st.runs.insert(id, run);
+        let events = futures::stream::pending::<Result<Bytes, Infallible>>();
+        return ([(header::CONTENT_TYPE, "text/event-stream")], Body::from_stream(events)).into_response();
+    }
+
+    if req["stream"] == true {
+        let asst_id = req["assistant_id"].as_str().unwrap_or_default();
+        let body = stream_run(&mut st, obj.clone(), final_status, &answer, asst_id);
+        obj["status"] = json!(final_status);
+        let run = MockRun {
+            obj,
+            statuses: Vec::new(),
+            answer,
+            tool_call,
+            tool_outputs: Vec::new(),
+        };
+        st.runs.insert(id, run);
+        return ([(header::CONTENT_TYPE, "text/event-stream")], body).into_response();
+    }
+
+    let statuses = match final_status {
+        "queued" => Vec::new(),
+        _ => vec![final_status, "in_progress"],
+    };
+    // The required action is only set once `requires_action` (on retrieve).
+    obj["required_action"] = Value::Null;
+    let run = MockRun {
+        obj: obj.clone(),
+        statuses,
+        answer,
+        tool_call,
+        tool_outputs: Vec::new(),
+    };
+    st.runs.insert(id, run);
+    Json(obj).into_response()
+}
+
+fn required_action(run_id: &str, tool_call: Option<&(String, String)>) -> Value {
+    let tool_calls: Vec<Value> = tool_call
+        .map(|(name, args)| {
+            json!({
+                "id": format!("call_{run_id}"),
+                "type": "function",
+                "function": { "name": name, "arguments": args },
+            })
+        })
+        .into_iter()
+        .collect();
+    json!({
+        "type": "submit_tool_outputs",
+        "submit_tool_outputs": { "tool_calls": tool_calls }
+    })
+}
+
+async fn submit_tool_outputs(
+    State(st): State<SharedState>,
+    Path((_thread_id, run_id)): Path<(String, String)>,
+    Json(req): Json<Value>,
+) -> Response {
+    let mut st = st.lock().unwrap();
+    let Some(run) = st.runs.get_mut(&run_id) else {
+        return not_found("run", &run_id);
+    };
+    if run.obj["status"] != "requires_action" {
+        let body = json!({ "error": {
+            "message": format!("Run {run_id} does not require tool outputs."),
+            "type": "invalid_request_error",
+            "param": null,
+            "code": null,
+        }});
+        return (StatusCode::BAD_REQUEST, Json(body)).into_response();
+    }
+
+    let outputs = req["tool_outputs"].as_array().cloned().unwrap_or_default();
+    let output = outputs.first().and_then(|o| o["output"].as_str()).unwrap_or_default();
+    run.answer = format!("tool output: {output}");
+    run.tool_outputs = outputs;
+    run.obj["required_action"] = Value::Null;
+    run.obj["status"] = json!("queued");
+
+    if req["stream"] == true {
+        let obj = run.obj.clone();
+        let answer = run.answer.clone();
+        run.obj["status"] = json!("completed");
+        let asst_id = obj["assistant_id"].as_str().unwrap_or_default().to_string();
+        let body = stream_run(&mut st, obj, "completed", &answer, &asst_id);
+        return ([(header::CONTENT_TYPE, "text/event-stream")], body).into_response();
+    }
+
+    run.statuses = vec!["completed", "in_progress"];
+    Json(run.obj.clone()).into_response()
+}
+
+async fn list_runs(State(st): State<SharedState>, Path(thread_id): Path<String>) -> Response {
+    let st = st.lock().unwrap();
+    let mut runs: Vec<Value> = st
+        .runs
+        .values()
+        .filter(|r| r.obj["thread_id"] == thread_id.as_str())
+        .map(|r| r.obj.clone())
+        .collect();
+    // Newest first (ids grow).
+    runs.sort_by(|a, b| b["id"].as_str().cmp(&a["id"].as_str()));
+
+    list_obj(runs).into_response()
+}
+
+/// Returns the whole SSE body of a streamed run (and adds the answer message).
+fn stream_run(
+    st: &mut MockState,
+    mut run: Value,
+    final_status: &str,
+    answer: &str,
+    asst_id: &str,
+) -> String {
+    let thread_id = run["thread_id"].as_str().unwrap_or_default().to_string();
+    let run_id = run["id"].as_str().unwrap_or_default().to_string();
+
+    let mut body = sse_event("thread.run.created", &run);
+    run["status"] = json!("in_progress");
+    body.push_str(&sse_event("thread.run.in_progress", &run));
+
+    if final_status == "completed" {
+        let msg = new_msg(st, &thread_id, "assistant", answer, Some((asst_id, &run_id)));
+        for (index, word) in answer.split_inclusive(' ').enumerate() {
+            let delta = json!({
+                "id": msg["id"],
+                "object": "thread.message.delta",
+                "delta": {
+                    "content": [{
+                        "index": index,
+                        "type": "text",
+                        "text": { "value": word }
+                    }]
+                }
+            });
+            body.push_str(&sse_event("thread.message.delta", &delta));
+        }
+        body.push_str(&sse_event("thread.message.completed", &msg));
+    } else if final_status == "requires_action" {
+        // The stream ends, the run continues in the submit tool outputs stream.
+        body.push_str(&sse_event("thread.run.requires_action", &run));
+        body.push_str("event: done\ndata: [DONE]\n\n");
+        return body;
+    } else if final_status == "failed" {
+        run["last_error"] = json!({
+            "code": "server_error",
+            "message": "Mock run failed.",
+        });
+    }
+
+    run["status"] = json!(final_status);
+    body.push_str(&sse_event(&format!("thread.run.{final_status}"), &run));
+    body.push_str("event: done\ndata: [DONE]\n\n");
+
+    body
+}
+
+fn sse_event(event: &str, data: &Value) -> String {
+    format!("event: {event}\ndata: {data}\n\n")
+}
+
+async fn get_run(
+    State(st): State<SharedState>,
+    Path((thread_id, run_id)): Path<(String, String)>,
+) -> Response {
+    let mut st = st.lock().unwrap();
+    let Some(run) = st.runs.get_mut(&run_id) else {
+        return not_found("run", &run_id);
+    };
+
+    // -- Advance the run one step.
+    if let Some(status) = run.statuses.pop() {
+        run.obj["status"] = json!(status);
+        if status == "requires_action" {
+            run.obj["required_action"] = required_action(&run_id, run.tool_call.as_ref());
+        }
+        if status == "failed" {
+            run.obj["last_error"] = json!({
+                "code": "server_error",
+                "message": "Mock run failed.",
+            });
+        }
+        if status == "completed" {
+            let answer = run.answer.clone();
+            let asst_id = run.obj["assistant_id"].as_str().unwrap_or_default().to_string();
+            let obj = run.obj.clone();
+            new_msg(&mut st, &thread_id, "assistant", &answer, Some((&asst_id, &run_id)));
+            return Json(obj).into_response();
+        }
+    }
+
+    Json(run.obj.clone()).into_response()
+}
+
+async fn cancel_run(
+    State(st): State<SharedState>,
+    Path((_thread_id, run_id)): Path<(String, String)>,
+) -> Response {
+    let mut st = st.lock().unwrap();
+    let Some(run) = st.runs.get_mut(&run_id) else {
+        return not_found("run", &run_id);
+    };
+    run.statuses = vec!["cancelled"];
+    run.obj["status"] = json!("cancelling");
+    Json(run.obj.clone()).into_response()
+}
+
+// endregion: --- Runs
+
+// region:    --- Chat Completions
+
+async fn chat_completions(State(st): State<SharedState>, Json(req): Json<Value>) -> Response {
+    let mut st = st.lock().unwrap();
+    let last_user_msg = req["messages"]
+        .as_array()
+        .and_then(|msgs| msgs.iter().rev().find(|m| m["role"] == "user"))
+        .and_then(|m| m["content"].as_str())
+        .unwrap_or_default()
+        .to_string();
+    let answer = format!("echo: {last_user_msg}");
+    let id = st.new_id("chatcmpl");
+    let stream = req["stream"] == true;
+    st.chat_requests.push(req);
+
+    if !stream {
+        return Json(json!({
+            "id": id,
+            "object": "chat.completion",
+            "created": 1700000000,
+            "model": "mock-model",
+            "system_fingerprint": null,
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": answer },
+                "finish_reason": "stop",
+                "logprobs": null,
+            }],
+            "usage": null,
+        }))
+        .into_response();
+    }
+
+    let mut body = String::new();
+    for word in answer.split_inclusive(' ') {
+        let chunk = json!({
+            "id": id,
+            "object": "chat.completion.chunk",
+            "created": 1700000000,
+            "model": "mock-model",
+            "system_fingerprint": null,
+            "choices": [{
+                "index": 0,
+                "delta": { "content": word },
+                "finish_reason": null,
+                "logprobs": null,
+            }],
+        });
+        body.push_str(&format!("data: {chunk}\n\n"));
+    }
+    body.push_str("data: [DONE]\n\n");
+
+    ([(header::CONTENT_TYPE, "text/event-stream")], body).into_response()
+}
+
+// endregion: --- Chat Completions
@@ -0,0 +1,97 @@
+// region:    --- Modules
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use tempfile::TempDir;
+
+use crate::Result;
+use crate::tools::ToolCtx;
+
+pub use self::mock_oa::{MockFault, MockFile, MockOaServer};
+
+mod mock_oa;
+
+// endregion: --- Modules
+
+// region:    --- Fixtures
+
+/// A temporary project with a `rusty_ai/` config dir (pointing to `api_base`),
+/// a `src/` with one rust file, and a `rusty_ai/files/` with one markdown file.
+/// (`with_config` inserts `extra_toml` top level properties in the config)
+pub struct TestProject {
+    _root: TempDir,
+    pub dir: PathBuf,
+}
+
+impl TestProject {
+    pub fn new(api_base: &str) -> Result<Self> {
+        Self::with_config(api_base, "")
+    }
+
+    pub fn with_config(api_base: &str, extra_toml: &str) -> Result<Self> {
+        let root = TempDir::new()?;
+        let dir = root.path().join("rusty_ai");
+
+        fs::create_dir_all(dir.join("files"))?;
+        fs::create_dir_all(root.path().join("src"))?;
+
+        fs::write(
+            dir.join("rusty_ai.toml"),
+            format!(
+                r#"name = "test-ai"
+model = "mock-model"
+instructions_file = "instructions.md"
+api_base = "{api_base}"
+{extra_toml}
+
+[[file_bundles]]
+bundle_name = "source-code"
+src_dir = "../src"
+src_globs = ["**/*.rs"]
+dst_ext = "txt"
+
+[[file_bundles]]
+bundle_name = "knowledge"
+src_dir = "files"
+src_globs = ["*.md"]
+dst_ext = "md"
+"#
+            ),
+        )?;
+        fs::write(dir.join("instructions.md"), "You are a test assistant.")?;
+        fs::write(dir.join("files/notes.md"), "# Notes\n\nSome knowledge.")?;
+        fs::write(root.path().join("src/main.rs"), "fn main() {}\n")?;
+
+        Ok(Self { _root: root, dir })
+    }
+}
+
+/// Writes a project in `root`: a `rusty_ai/` config dir, and the `files`
+/// (`(path relative to root, content)`, their parent dirs created).
+pub fn write_src_project(root: &Path, files: &[(&str, &str)]) -> Result<()> {
+    fs::create_dir_all(root.join("rusty_ai"))?;
+    for (path, content) in files {
+        let file = root.join(path);
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(file, content)?;
+    }
+
+    Ok(())
+}
+
+/// The `ToolCtx` of the project in `root` (see `write_src_project`).
+pub fn tool_ctx(root: &Path) -> ToolCtx {
+    ToolCtx {
+        root: root.to_path_buf(),
+        config_dir: root.join("rusty_ai"),
+        index_file: root.join("index.json"),
+        cargo: Default::default(),
+        target_dir: root.join("rusty_ai/.rusty_ai/target"),
+        cancel: Default::default(),
+    }
+}
+
+// endregion: --- Fixtures
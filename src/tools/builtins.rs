@@ -0,0 +1,464 @@
+//! The local handlers of the tools.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use serde_json::{json, Value};
+
+use crate::{Error, FileOp, Result};
+use crate::retrieval::Index;
+use crate::rusty_ai::DATA_DIR;
+use crate::utils::files::{base_dir_exclude_globs, file_io, get_glob_set, list_files};
+
+use super::{run_cargo, CargoCmd, ToolCtx, ToolFn};
+
+// region:    --- Registry
+
+pub(super) struct Builtin {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Returns the parameters JSON schema.
+    pub parameters: fn() -> Value,
+    pub handler: ToolFn,
+}
+
+const BUILTINS: &[Builtin] = &[
+    Builtin {
+        name: "search_code",
+        description: "Searches the project files, returns the most relevant excerpts with their file path and line.",
+        parameters: search_code_params,
+        handler: search_code,
+    },
+    Builtin {
+        name: "read_file",
+        description: "Reads the current content of a project file (or a range of lines), with line numbers.",
+        parameters: read_file_params,
+        handler: read_file,
+    },
+    Builtin {
+        name: "grep",
+        description: "Searches a regex in the project files, returns the matching lines with their file path and line.",
+        parameters: grep_params,
+        handler: grep,
+    },
+    Builtin {
+        name: "cargo",
+        description: "Runs `cargo check`, `cargo test` or `cargo clippy` on the project, returns the diagnostics (and failed tests output) as JSON.",
+        parameters: cargo_params,
+        handler: cargo,
+    },
+    Builtin {
+        name: "list_dir",
+        description: "Lists the files and sub directories (ending with '/') of a project directory.",
+        parameters: list_dir_params,
+        handler: list_dir,
+    },
+];
+
+pub(super) fn find_builtin(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|b| b.name == name)
+}
+
+// endregion: --- Registry
+
+// region:    --- Search Code
+
+const SEARCH_CODE_HITS: usize = 5;
+
+fn search_code_params() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "query": { "type": "string", "description": "Words to search, e.g., function or type names" }
+        },
+        "required": ["query"]
+    })
+}
+
+fn search_code(ctx: &ToolCtx, args: &Value) -> Result<String> {
+    let query = args["query"].as_str().ok_or(Error::ToolArgMissing { name: "query" })?;
+    let index = Index::load(&ctx.index_file).map_err(|_| Error::NoSearchIndex)?;
+
+    let hits = index.search(query, SEARCH_CODE_HITS);
+    if hits.is_empty() {
+        return Ok("No match".to_string());
+    }
+
+    let output = hits
+        .iter()
+        .map(|hit| format!("{}:{}\n{}", hit.chunk.path, hit.chunk.start_line, hit.chunk.content))
+        .collect::<Vec<_>>()
+        .join("\n\n");
+
+    Ok(output)
+}
+
+// endregion: --- Search Code
+
+// region:    --- Read File
+
+const READ_FILE_MAX_LINES: usize = 400;
+
+fn read_file_params() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "path": { "type": "string", "description": "File path, relative to the project root" },
+            "start_line": { "type": "integer", "description": "First line to read (from 1, default 1)" },
+            "end_line": { "type": "integer", "description": "Last line to read (included, default end of file)" }
+        },
+        "required": ["path"]
+    })
+}
+
+fn read_file(ctx: &ToolCtx, args: &Value) -> Result<String> {
+    let path = args["path"].as_str().ok_or(Error::ToolArgMissing { name: "path" })?;
+    let file = resolve_path(ctx, path)?;
+    if !file.is_file() {
+        return Err(Error::PathNotFile { path: path.to_string() });
+    }
+    let content = fs::read_to_string(&file).map_err(file_io(FileOp::Read, &file))?;
+
+    let lines: Vec<&str> = content.lines().collect();
+    let start = args["start_line"].as_u64().unwrap_or(1).max(1) as usize;
+    let end = args["end_line"].as_u64().map_or(lines.len(), |end| end as usize).min(lines.len());
+    if start > end {
+        return Ok(format!("{path} has {} lines", lines.len()));
+    }
+    let end = end.min(start + READ_FILE_MAX_LINES - 1);
+
+    let mut output = format!("{path} (lines {start}-{end} of {})\n", lines.len());
+    for (num, line) in lines[start - 1..end].iter().enumerate() {
+        output.push_str(&format!("{:>5} | {line}\n", start + num));
+    }
+
+    Ok(output)
+}
+
+// endregion: --- Read File
+
+// region:    --- Grep
+
+const GREP_MAX_HITS: usize = 100;
+
+fn grep_params() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "pattern": { "type": "string", "description": "Regex to search (Rust regex syntax)" },
+            "path": { "type": "string", "description": "Directory or file to search in, relative to the project root (default the root)" },
+            "glob": { "type": "string", "description": "Only search the files matching this glob, e.g., '*.rs'" }
+        },
+        "required": ["pattern"]
+    })
+}
+
+fn grep(ctx: &ToolCtx, args: &Value) -> Result<String> {
+    let pattern = args["pattern"].as_str().ok_or(Error::ToolArgMissing { name: "pattern" })?;
+    let regex = Regex::new(pattern).map_err(Error::InvalidRegex)?;
+    let path = resolve_path(ctx, args["path"].as_str().unwrap_or("."))?;
+    let root = ctx.root.canonicalize()?;
+
+    let files = if path.is_file() {
+        vec![path]
+    } else {
+        let glob = args["glob"].as_str().map(|g| format!("**/{g}"));
+        let include = [glob.as_deref().unwrap_or("**/*")];
+        list_files(&path, Some(&include), None)?
+    };
+    let data_dir = data_dir(ctx);
+    let files = files.into_iter().filter(|file| !is_in_data_dir(data_dir.as_deref(), file));
+
+    let mut hits = Vec::new();
+    for file in files {
+        // Note: the non UTF-8 (binary) files are skipped.
+        let Ok(content) = fs::read_to_string(&file) else {
+            continue;
+        };
+        for (num, line) in content.lines().enumerate() {
+            if regex.is_match(line) {
+                hits.push(format!("{}:{}: {}", rel_path(&root, &file), num + 1, line.trim_end()));
+            }
+        }
+    }
+
+    let mut output = match hits.len() {
+        0 => return Ok("No match".to_string()),
+        n if n > GREP_MAX_HITS => format!("{n} matches, the first {GREP_MAX_HITS}:\n"),
+        _ => String::new(),
+    };
+    output.push_str(&hits[..hits.len().min(GREP_MAX_HITS)].join("\n"));
+
+    Ok(output)
+}
+
+// endregion: --- Grep
+
+// region:    --- List Dir
+
+fn list_dir_params() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "path": { "type": "string", "description": "Directory path, relative to the project root (default the root)" }
+        }
+    })
+}
+
+fn list_dir(ctx: &ToolCtx, args: &Value) -> Result<String> {
+    let path = args["path"].as_str().unwrap_or(".");
+    let dir = resolve_path(ctx, path)?;
+    if !dir.is_dir() {
+        return Err(Error::PathNotDir { path: path.to_string() });
+    }
+    let exclude = base_dir_exclude_globs()?;
+    let data_dir = data_dir(ctx);
+
+    let mut entries = Vec::new();
+    for entry in fs::read_dir(&dir).map_err(file_io(FileOp::Read, &dir))?.filter_map(|e| e.ok()) {
+        let entry_path = entry.path();
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry_path.is_dir() {
+            if !exclude.is_match(&name) && !is_in_data_dir(data_dir.as_deref(), &entry_path) {
+                entries.push(format!("{name}/"));
+            }
+        } else {
+            entries.push(name);
+        }
+    }
+    entries.sort();
+
+    if entries.is_empty() {
+        return Ok(format!("{path} is empty"));
+    }
+
+    Ok(entries.join("\n"))
+}
+
+// endregion: --- List Dir
+
+// region:    --- Cargo
+
+fn cargo_params() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "command": { "type": "string", "enum": ["check", "test", "clippy"] }
+        },
+        "required": ["command"]
+    })
+}
+
+fn cargo(ctx: &ToolCtx, args: &Value) -> Result<String> {
+    let cmd: CargoCmd = serde_json::from_value(args["command"].clone())
+        .map_err(|_| Error::ToolArgInvalid { name: "command", expected: "check, test or clippy" })?;
+    if cmd == CargoCmd::Test && !ctx.cargo.allow_test {
+        return Err(Error::CargoTestNotAllowed);
+    }
+    let report = run_cargo(ctx, cmd)?;
+
+    Ok(serde_json::to_string(&report)?)
+}
+
+// endregion: --- Cargo
+
+// region:    --- Paths
+
+/// Returns the canonical path of the project relative `path`.
+/// Errors if it does not exist, is out of the project root or excluded
+/// (`.git`, `target` and the config data dir).
+/// Note: the excludes are matched from the root (which can be under a `target` dir).
+fn resolve_path(ctx: &ToolCtx, path: &str) -> Result<PathBuf> {
+    let root = ctx.root.canonicalize()?;
+    let full = root
+        .join(path)
+        .canonicalize()
+        .map_err(|_| Error::PathNotFound { path: path.to_string() })?;
+
+    let Ok(rel) = full.strip_prefix(&root) else {
+        return Err(Error::PathOutOfRoot { path: path.to_string() });
+    };
+    let exclude = get_glob_set(&["**/.git/**", "**/target/**"])?;
+    if base_dir_exclude_globs()?.is_match(rel)
+        || exclude.is_match(rel)
+        || is_in_data_dir(data_dir(ctx).as_deref(), &full)
+    {
+        return Err(Error::PathExcluded { path: path.to_string() });
+    }
+
+    Ok(full)
+}
+
+/// Returns the canonical config data dir (bundle copies, index, conversations,
+/// prompt history), never exposed by the file tools (`None` if not created yet).
+fn data_dir(ctx: &ToolCtx) -> Option<PathBuf> {
+    ctx.config_dir.join(DATA_DIR).canonicalize().ok()
+}
+
+/// Note: `path` must be canonical (as the `data_dir`).
+fn is_in_data_dir(data_dir: Option<&Path>, path: &Path) -> bool {
+    data_dir.is_some_and(|data_dir| path.starts_with(data_dir))
+}
+
+/// Returns the path relative to the (canonical) project `root`, for display.
+fn rel_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .to_string_lossy()
+        .replace('\\', "/")
+}
+
+// endregion: --- Paths
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Result<T> = core::result::Result<T, Error>; // For tests.
+
+    use super::*;
+    use crate::test_support::{tool_ctx, write_src_project};
+    use tempfile::TempDir;
+
+    fn fx_project() -> Result<(TempDir, ToolCtx)> {
+        let tmp = TempDir::new()?;
+        let ctx = fx_project_at(tmp.path())?;
+
+        Ok((tmp, ctx))
+    }
+
+    fn fx_project_at(root: &Path) -> Result<ToolCtx> {
+        write_src_project(root, &[
+            ("src/main.rs", "mod utils;\n\nfn main() {\n    run();\n}\n"),
+            ("src/utils/mod.rs", "pub fn run() {}\n"),
+            ("target/debug/out.rs", "fn run() {}\n"),
+            ("README.md", "Call run() to run.\n"),
+        ])?;
+
+        Ok(tool_ctx(root))
+    }
+
+    #[test]
+    fn test_read_file_range_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let (_root, ctx) = fx_project()?;
+
+        // -- Exec
+        let output = read_file(&ctx, &json!({ "path": "src/main.rs", "start_line": 3, "end_line": 9 }))?;
+
+        // -- Check
+        assert_eq!(output, "src/main.rs (lines 3-5 of 5)\n    3 | fn main() {\n    4 |     run();\n    5 | }\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_grep_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let (_root, ctx) = fx_project()?;
+
+        // -- Exec
+        let all = grep(&ctx, &json!({ "pattern": r"\brun\(" }))?;
+        let rs_only = grep(&ctx, &json!({ "pattern": r"fn \w+", "path": "src", "glob": "*.rs" }))?;
+
+        // -- Check
+        let mut all: Vec<&str> = all.lines().collect();
+        all.sort();
+        assert_eq!(
+            all,
+            [
+                "README.md:1: Call run() to run.",
+                "src/main.rs:4:     run();",
+                "src/utils/mod.rs:1: pub fn run() {}",
+            ]
+        );
+        let mut rs_only: Vec<&str> = rs_only.lines().collect();
+        rs_only.sort();
+        assert_eq!(rs_only, ["src/main.rs:3: fn main() {", "src/utils/mod.rs:1: pub fn run() {}"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_list_dir_excludes_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let (_root, ctx) = fx_project()?;
+
+        // -- Exec
+        let root_entries = list_dir(&ctx, &json!({}))?;
+        let src_entries = list_dir(&ctx, &json!({ "path": "src" }))?;
+
+        // -- Check
+        assert_eq!(root_entries, "README.md\nrusty_ai/\nsrc/");
+        assert_eq!(src_entries, "main.rs\nutils/");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_project_under_target_dir_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let tmp = TempDir::new()?;
+        let ctx = fx_project_at(&tmp.path().join("target/project"))?;
+
+        // -- Exec
+        let main = read_file(&ctx, &json!({ "path": "src/main.rs", "end_line": 1 }))?;
+        let hits = grep(&ctx, &json!({ "pattern": r"pub fn run" }))?;
+        let out = read_file(&ctx, &json!({ "path": "target/debug/out.rs" }));
+
+        // -- Check
+        assert_eq!(main, "src/main.rs (lines 1-1 of 5)\n    1 | mod utils;\n");
+        assert_eq!(hits, "src/utils/mod.rs:1: pub fn run() {}");
+        assert!(out.is_err(), "the project target dir is still excluded");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_data_dir_excluded_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let (root, ctx) = fx_project()?;
+        let data_dir = root.path().join("rusty_ai/.rusty_ai");
+        fs::create_dir_all(data_dir.join("files"))?;
+        fs::create_dir_all(data_dir.join("convs"))?;
+        fs::write(data_dir.join("files/src.rs"), "// ==== file path: src/utils/mod.rs\n\npub fn run() {}\n")?;
+        fs::write(data_dir.join("convs/default.json"), "{}")?;
+
+        // -- Exec
+        let hits = grep(&ctx, &json!({ "pattern": r"pub fn run" }))?;
+        let config_entries = list_dir(&ctx, &json!({ "path": "rusty_ai" }))?;
+        let bundle = read_file(&ctx, &json!({ "path": "rusty_ai/.rusty_ai/files/src.rs" }));
+        let conv_dir = list_dir(&ctx, &json!({ "path": "rusty_ai/.rusty_ai/convs" }));
+
+        // -- Check
+        assert_eq!(hits, "src/utils/mod.rs:1: pub fn run() {}");
+        assert_eq!(config_entries, "rusty_ai is empty");
+        let err = bundle.err().ok_or("bundle should not be readable")?;
+        assert!(err.to_string().contains("is excluded"), "{err}");
+        assert!(conv_dir.is_err(), "convs should not be listed");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_paths_out_of_root_err() -> Result<()> {
+        // -- Setup & Fixtures
+        let (_root, ctx) = fx_project()?;
+
+        // -- Exec & Check
+        for (path, expected) in [
+            ("../", "out of the project root"),
+            ("target/debug/out.rs", "is excluded"),
+            ("src/nope.rs", "No file or directory"),
+        ] {
+            let err = read_file(&ctx, &json!({ "path": path })).err().ok_or("should fail")?;
+            assert!(err.to_string().contains(expected), "{path}: {err}");
+        }
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
@@ -0,0 +1,502 @@
+//! Runs `cargo check|test|clippy` in the project, and reports the diagnostics.
+//!
+//! The assistant can only ask these 3 commands with fixed arguments, in the
+//! project dir, and the report is capped to `max_output_bytes`.
+//! Cargo runs `--offline`, with its own target dir (`ToolCtx::target_dir`, so
+//! the project one is not locked nor rebuilt), and without the api keys in its
+//! environment. Its whole process group (rustc, build scripts, tests) is killed
+//! after `timeout_secs` or once the call is cancelled.
+//!
+//! Note: this is not a sandbox, the build scripts and proc macros of the project
+//!       run as the user (and its tests for `cargo test`, so only allowed to
+//!       the assistant with `allow_test`).
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::Ordering;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use derive_more::Display;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::ToolCtx;
+use crate::{Error, Result};
+
+// region:    --- Types
+
+#[derive(Debug, Clone, Copy, Display, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CargoCmd {
+    #[display(fmt = "check")]
+    Check,
+    #[display(fmt = "test")]
+    Test,
+    #[display(fmt = "clippy")]
+    Clippy,
+}
+
+/// The `[cargo]` table of `rusty_ai.toml`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CargoConfig {
+    /// The command is killed after it (the report then has `timed_out`)
+    pub timeout_secs: u64,
+    /// Cap of the report diagnostics and test output
+    pub max_output_bytes: usize,
+    /// The `cargo` tool can run `cargo test` (the project tests code)
+    pub allow_test: bool,
+}
+
+impl Default for CargoConfig {
+    fn default() -> Self {
+        Self {
+            timeout_secs: 180,
+            max_output_bytes: 16_000,
+            allow_test: false,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct CargoReport {
+    pub command: CargoCmd,
+    pub success: bool,
+    pub timed_out: bool,
+    pub errors: usize,
+    pub warnings: usize,
+    pub diagnostics: Vec<Diagnostic>,
+    /// The failed tests output (`cargo test` only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub test_output: Option<String>,
+    /// Some diagnostics or output were dropped (`max_output_bytes`)
+    pub truncated: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Diagnostic {
+    pub level: String,
+    pub message: String,
+    pub code: Option<String>,
+    /// Relative to the cargo project dir.
+    pub file: Option<String>,
+    pub line: Option<u64>,
+    pub column: Option<u64>,
+    /// The compiler human readable form (with the code excerpt)
+    pub rendered: Option<String>,
+}
+
+// endregion: --- Types
+
+// region:    --- Run
+
+/// The env variables not passed to cargo (and so to the project code).
+const HIDDEN_ENV: &[&str] = &["OPENAI_API_KEY"];
+
+/// Runs the cargo `cmd` of the cargo project containing `ctx.root` (the first
+/// ancestor with a `Cargo.toml`, up to the one of the `ctx.config_dir`).
+/// Returns `Error::CmdInterrupted` once `ctx.cancel` is set (cargo killed).
+pub fn run_cargo(ctx: &ToolCtx, cmd: CargoCmd) -> Result<CargoReport> {
+    let config = &ctx.cargo;
+    let project_dir = find_project_dir(&ctx.root, &ctx.config_dir)
+        .ok_or_else(|| Error::NoCargoProject { dir: ctx.root.to_path_buf() })?;
+
+    let mut args = vec![cmd.to_string(), "--message-format=json".to_string(), "--offline".to_string()];
+    if cmd == CargoCmd::Test {
+        args.push("--no-fail-fast".to_string());
+    }
+
+    let mut command = Command::new("cargo");
+    command
+        .args(&args)
+        .current_dir(&project_dir)
+        .env("CARGO_TERM_COLOR", "never")
+        .env("CARGO_TARGET_DIR", &ctx.target_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    for name in HIDDEN_ENV {
+        command.env_remove(name);
+    }
+    #[cfg(unix)]
+    std::os::unix::process::CommandExt::process_group(&mut command, 0);
+    let mut child = command.spawn().map_err(Error::CargoStart)?;
+
+    let stdout = read_in_thread(child.stdout.take());
+    let stderr = read_in_thread(child.stderr.take());
+
+    // -- Wait until done, timeout or cancel
+    let deadline = Instant::now() + Duration::from_secs(config.timeout_secs);
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break Some(status);
+        }
+        if ctx.cancel.load(Ordering::Relaxed) {
+            kill_all(&mut child);
+            return Err(Error::CmdInterrupted);
+        }
+        if Instant::now() >= deadline {
+            kill_all(&mut child);
+            break None;
+        }
+        thread::sleep(Duration::from_millis(100));
+    };
+
+    // Note: killed, the cargo sub processes might still hold the pipes.
+    let wait = Duration::from_secs(if status.is_some() { 10 } else { 1 });
+    let stdout = stdout.recv_timeout(wait).unwrap_or_default();
+    let stderr = stderr.recv_timeout(wait).unwrap_or_default();
+
+    let mut report = parse_output(cmd, &stdout, &project_dir, config.max_output_bytes);
+    report.success = status.is_some_and(|s| s.success());
+    report.timed_out = status.is_none();
+
+    // -- Cargo own errors (e.g., invalid manifest)
+    if !report.success && !report.timed_out && report.errors == 0 && report.test_output.is_none() {
+        report.test_output = Some(tail(stderr.trim(), config.max_output_bytes).to_string());
+    }
+
+    Ok(report)
+}
+
+/// Returns the first ancestor of `root` with a `Cargo.toml`, not going above
+/// the dir containing the `config_dir` (e.g., the project with `rusty_ai/`).
+fn find_project_dir(root: &Path, config_dir: &Path) -> Option<PathBuf> {
+    let root = root.canonicalize().ok()?;
+    let config_dir = config_dir.canonicalize().unwrap_or_else(|_| config_dir.to_path_buf());
+
+    for dir in root.ancestors() {
+        if dir.join("Cargo.toml").is_file() {
+            return Some(dir.to_path_buf());
+        }
+        if config_dir.starts_with(dir) {
+            break;
+        }
+    }
+
+    None
+}
+
+/// Kills cargo and its sub processes (its process group on unix).
+fn kill_all(child: &mut Child) {
+    #[cfg(unix)]
+    if let Ok(pgid) = libc::pid_t::try_from(child.id()) {
+        // SAFETY: plain syscall, the group being the one created for the child.
+        unsafe {
+            libc::kill(-pgid, libc::SIGKILL);
+        }
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+fn read_in_thread(pipe: Option<impl Read + Send + 'static>) -> mpsc::Receiver<String> {
+    let (tx, rx) = mpsc::channel();
+    if let Some(mut pipe) = pipe {
+        thread::spawn(move || {
+            let mut out = Vec::new();
+            let _ = pipe.read_to_end(&mut out);
+            let _ = tx.send(String::from_utf8_lossy(&out).to_string());
+        });
+    }
+    rx
+}
+
+// endregion: --- Run
+
+// region:    --- Parse
+
+/// Returns the report of the `--message-format=json` output (status fields unset).
+/// - The compiler messages are the diagnostics (deduped, summaries skipped).
+/// - The other lines are the test harness output (passing tests skipped).
+fn parse_output(cmd: CargoCmd, stdout: &str, project_dir: &Path, max_bytes: usize) -> CargoReport {
+    let mut diagnostics: Vec<Diagnostic> = Vec::new();
+    let mut test_lines = Vec::new();
+
+    for line in stdout.lines() {
+        let Ok(msg) = serde_json::from_str::<Value>(line) else {
+            if !(line.starts_with("test ") && line.ends_with("... ok")) {
+                test_lines.push(line);
+            }
+            continue;
+        };
+        if msg["reason"] != "compiler-message" {
+            continue;
+        }
+        let Some(diagnostic) = to_diagnostic(&msg["message"], project_dir) else {
+            continue;
+        };
+        if !diagnostics.contains(&diagnostic) {
+            diagnostics.push(diagnostic);
+        }
+    }
+
+    let errors = diagnostics.iter().filter(|d| d.level == "error").count();
+    let warnings = diagnostics.iter().filter(|d| d.level == "warning").count();
+
+    // -- Cap the output (errors first)
+    diagnostics.sort_by_key(|d| d.level != "error");
+    let mut truncated = false;
+    let mut size = 0;
+    diagnostics.retain(|d| {
+        size += d.rendered.as_ref().map_or(d.message.len(), String::len);
+        truncated |= size > max_bytes;
+        size <= max_bytes
+    });
+
+    let test_output = (cmd == CargoCmd::Test).then(|| {
+        let output = test_lines.join("\n");
+        let output = output.trim();
+        let capped = tail(output, max_bytes.saturating_sub(size));
+        truncated |= capped.len() < output.len();
+        capped.to_string()
+    });
+
+    CargoReport {
+        command: cmd,
+        success: false,
+        timed_out: false,
+        errors,
+        warnings,
+        diagnostics,
+        test_output: test_output.filter(|o| !o.is_empty()),
+        truncated,
+    }
+}
+
+fn to_diagnostic(msg: &Value, project_dir: &Path) -> Option<Diagnostic> {
+    let message = msg["message"].as_str()?.to_string();
+    let spans = msg["spans"].as_array().map(Vec::as_slice).unwrap_or_default();
+    let span = spans.iter().find(|s| s["is_primary"] == true).or(spans.first());
+
+    // Skip the summaries, e.g., "aborting due to 2 previous errors".
+    if span.is_none() && (message.starts_with("aborting due to") || message.ends_with("emitted")) {
+        return None;
+    }
+
+    let file = span.and_then(|s| s["file_name"].as_str()).map(|file| {
+        Path::new(file)
+            .strip_prefix(project_dir)
+            .unwrap_or(Path::new(file))
+            .to_string_lossy()
+            .to_string()
+    });
+
+    Some(Diagnostic {
+        level: msg["level"].as_str().unwrap_or_default().to_string(),
+        message,
+        code: msg["code"]["code"].as_str().map(String::from),
+        file,
+        line: span.and_then(|s| s["line_start"].as_u64()),
+        column: span.and_then(|s| s["column_start"].as_u64()),
+        rendered: msg["rendered"].as_str().map(|r| r.trim_end().to_string()),
+    })
+}
+
+/// Returns the end of `text`, at most `max_bytes` (on a char boundary).
+fn tail(text: &str, max_bytes: usize) -> &str {
+    let mut start = text.len().saturating_sub(max_bytes);
+    while !text.is_char_boundary(start) {
+        start += 1;
+    }
+    &text[start..]
+}
+
+// endregion: --- Parse
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Result<T> = core::result::Result<T, Error>; // For tests.
+
+    use super::*;
+    use crate::test_support::{tool_ctx, write_src_project};
+    use serde_json::json;
+    use std::fs;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+    use tempfile::TempDir;
+
+    fn fx_ctx(root: &Path) -> ToolCtx {
+        ToolCtx { root: root.join("src"), ..tool_ctx(root) }
+    }
+
+    fn fx_crate(root: &Path, build_rs: Option<&str>) -> Result<()> {
+        write_src_project(root, &[
+            ("Cargo.toml", "[package]\nname = \"fx-crate\"\nversion = \"0.1.0\"\nedition = \"2021\"\n"),
+            ("src/main.rs", "fn main() {\n    let x: u32 = \"a\";\n}\n"),
+        ])?;
+        if let Some(build_rs) = build_rs {
+            fs::write(root.join("build.rs"), build_rs)?;
+        }
+        Ok(())
+    }
+
+    /// Returns whether `file` exists within `timeout`.
+    fn wait_for_file(file: &Path, timeout: Duration) -> bool {
+        let start = Instant::now();
+        while start.elapsed() < timeout {
+            if file.exists() {
+                return true;
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        file.exists()
+    }
+
+    fn fx_compiler_msg(level: &str, message: &str, line: u64) -> String {
+        json!({
+            "reason": "compiler-message",
+            "message": {
+                "message": message,
+                "level": level,
+                "code": { "code": "E0308" },
+                "spans": [{ "file_name": "src/main.rs", "line_start": line, "column_start": 5, "is_primary": true }],
+                "rendered": format!("{level}: {message}\n --> src/main.rs:{line}:5\n"),
+            }
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_parse_output_test_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let warning = fx_compiler_msg("warning", "unused variable: `x`", 2);
+        let fx_stdout = [
+            r#"{"reason":"compiler-artifact"}"#,
+            &warning,
+            &warning,
+            r#"{"reason":"compiler-message","message":{"message":"1 warning emitted","level":"warning","spans":[],"code":null,"rendered":"warning: 1 warning emitted"}}"#,
+            "running 2 tests",
+            "test tests::ok ... ok",
+            "test tests::ko ... FAILED",
+            "test result: FAILED. 1 passed; 1 failed",
+        ]
+        .join("\n");
+
+        // -- Exec
+        let report = parse_output(CargoCmd::Test, &fx_stdout, Path::new("/p"), 1000);
+
+        // -- Check
+        assert_eq!((report.errors, report.warnings), (0, 1));
+        assert_eq!(report.diagnostics[0].file.as_deref(), Some("src/main.rs"));
+        assert_eq!(report.diagnostics[0].line, Some(2));
+        assert_eq!(
+            report.test_output.as_deref(),
+            Some("running 2 tests\ntest tests::ko ... FAILED\ntest result: FAILED. 1 passed; 1 failed")
+        );
+        assert!(!report.truncated);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_output_capped_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let fx_stdout = [
+            fx_compiler_msg("warning", "unused import", 1),
+            fx_compiler_msg("error", "mismatched types", 3),
+        ]
+        .join("\n");
+
+        // -- Exec
+        let report = parse_output(CargoCmd::Check, &fx_stdout, Path::new("/p"), 60);
+
+        // -- Check
+        assert_eq!((report.errors, report.warnings), (1, 1));
+        assert_eq!(report.diagnostics.len(), 1);
+        assert_eq!(report.diagnostics[0].message, "mismatched types");
+        assert!(report.truncated);
+        assert!(report.test_output.is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_find_project_dir_up_to_config_dir_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let tmp = TempDir::new()?;
+        let project = tmp.path().join("project");
+        fs::create_dir_all(project.join("src"))?;
+        fs::create_dir_all(project.join("rusty_ai"))?;
+        fs::write(tmp.path().join("Cargo.toml"), "[workspace]\n")?;
+
+        // -- Exec & Check
+        let outer = find_project_dir(&project.join("src"), &project.join("rusty_ai"));
+        assert_eq!(outer, None, "the Cargo.toml above the project is not used");
+
+        fs::write(project.join("Cargo.toml"), "[package]\n")?;
+        let found = find_project_dir(&project.join("src"), &project.join("rusty_ai"));
+        assert_eq!(found, Some(project.canonicalize()?));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_cargo_check_err() -> Result<()> {
+        // -- Setup & Fixtures
+        let root = TempDir::new()?;
+        fx_crate(root.path(), None)?;
+
+        // -- Exec
+        let report = run_cargo(&fx_ctx(root.path()), CargoCmd::Check)?;
+
+        // -- Check
+        assert!(!report.success);
+        assert!(!report.timed_out);
+        assert!(root.path().join("rusty_ai/.rusty_ai/target").is_dir());
+        assert!(!root.path().join("target").exists(), "the project target dir is not used");
+        assert_eq!(report.errors, 1);
+        let error = report.diagnostics.iter().find(|d| d.level == "error").ok_or("no error")?;
+        assert_eq!(error.code.as_deref(), Some("E0308"));
+        assert_eq!(error.file.as_deref(), Some("src/main.rs"));
+        assert_eq!(error.line, Some(2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_run_cargo_cancel_kills_group_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        // The build script outlives cargo, unless its process group is killed.
+        let root = TempDir::new()?;
+        let started = root.path().join("started.txt");
+        let alive = root.path().join("alive.txt");
+        let build_rs = format!(
+            "fn main() {{\n    std::fs::write({started:?}, \"started\").unwrap();\n    std::thread::sleep(std::time::Duration::from_secs(2));\n    std::fs::write({alive:?}, \"alive\").unwrap();\n}}\n"
+        );
+        fx_crate(root.path(), Some(&build_rs))?;
+        let ctx = fx_ctx(root.path());
+        let cancel: Arc<AtomicBool> = ctx.cancel.clone();
+        let started_file = started.clone();
+        let canceller = thread::spawn(move || {
+            // Note: cancels once the build script runs (bounded, so a broken
+            //       build can not hang the test).
+            let is_started = wait_for_file(&started_file, Duration::from_secs(120));
+            cancel.store(true, Ordering::Relaxed);
+            is_started
+        });
+
+        // -- Exec
+        let res = run_cargo(&ctx, CargoCmd::Check);
+        let is_started = canceller.join().map_err(|_| "canceller panicked")?;
+
+        // -- Check
+        assert!(is_started, "the build script should have started");
+        assert!(matches!(res, Err(crate::Error::CmdInterrupted)), "{res:?}");
+        // Note: past the 2s sleep of the build script.
+        assert!(
+            !wait_for_file(&alive, Duration::from_secs(4)),
+            "the build script should be killed"
+        );
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
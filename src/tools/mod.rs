@@ -0,0 +1,160 @@
+//! Function tools the assistant can call, executed locally.
+//!
+//! The tools are declared in the `[[tools]]` of `rusty_ai.toml`, each `name`
+//! being the one of a local handler (see `builtins`).
+//! The file tools (`read_file`, `grep`, `list_dir`) are restricted to the
+//! project root (see `ToolCtx::root`, the config `.rusty_ai/` data excluded),
+//! and `cargo` runs in its cargo project.
+
+// region:    --- Modules
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use async_openai::types::FunctionObject;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{Error, Result};
+use crate::utils::cli::ico_tool;
+
+use self::builtins::find_builtin;
+
+pub use self::cargo::{run_cargo, CargoCmd, CargoConfig, CargoReport};
+
+mod builtins;
+mod cargo;
+
+// endregion: --- Modules
+
+// region:    --- Types
+
+/// A `[[tools]]` entry of `rusty_ai.toml`.
+/// (`description` and `parameters` JSON schema default to the handler ones)
+#[derive(Debug, Clone, Deserialize)]
+pub struct ToolDef {
+    pub name: String,
+    pub description: Option<String>,
+    pub parameters: Option<Value>,
+}
+
+/// What the handlers can access.
+#[derive(Debug, Clone)]
+pub struct ToolCtx {
+    /// The project root, common dir of the `file_bundles.src_dir` (the file
+    /// tools paths are relative to it, and cannot go out of it)
+    pub root: PathBuf,
+    /// The config dir (the `cargo` project is searched up to its parent)
+    pub config_dir: PathBuf,
+    /// The local search index (see `retrieval`)
+    pub index_file: PathBuf,
+    /// The `[cargo]` limits of the cargo tool
+    pub cargo: CargoConfig,
+    /// The cargo target dir (in `.rusty_ai/`, apart from the project one)
+    pub target_dir: PathBuf,
+    /// Set once the call is dropped (see `for_call`), the long handlers then stop
+    pub cancel: Arc<AtomicBool>,
+}
+
+/// Sets the `ToolCtx::cancel` flag of its call when dropped.
+pub struct CancelGuard(Arc<AtomicBool>);
+
+impl Drop for CancelGuard {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Local handler, returns the output given to the assistant.
+pub type ToolFn = fn(&ToolCtx, &Value) -> Result<String>;
+
+/// The declared tools and their handlers.
+#[derive(Debug)]
+pub struct Tools {
+    functions: Vec<FunctionObject>,
+    handlers: HashMap<String, ToolFn>,
+    ctx: ToolCtx,
+}
+
+// endregion: --- Types
+
+// region:    --- Tools
+
+impl ToolCtx {
+    /// Returns the context of one call, cancelled when the guard is dropped
+    /// (e.g., the call future dropped on Ctrl-C).
+    pub fn for_call(&self) -> (ToolCtx, CancelGuard) {
+        let cancel = Arc::new(AtomicBool::new(false));
+        let ctx = ToolCtx { cancel: cancel.clone(), ..self.clone() };
+        (ctx, CancelGuard(cancel))
+    }
+}
+
+impl Tools {
+    /// Returns an error if a tool has no local handler.
+    pub fn new(defs: &[ToolDef], ctx: ToolCtx) -> Result<Self> {
+        let mut functions = Vec::new();
+        let mut handlers = HashMap::new();
+
+        for def in defs {
+            let builtin = find_builtin(&def.name)
+                .ok_or_else(|| Error::NoToolHandler { name: def.name.clone() })?;
+
+            functions.push(FunctionObject {
+                name: def.name.clone(),
+                description: def
+                    .description
+                    .clone()
+                    .or_else(|| Some(builtin.description.to_string())),
+                parameters: def.parameters.clone().or_else(|| Some((builtin.parameters)())),
+            });
+            handlers.insert(def.name.clone(), builtin.handler);
+        }
+
+        Ok(Self {
+            functions,
+            handlers,
+            ctx,
+        })
+    }
+
+    pub fn ctx(&self) -> &ToolCtx {
+        &self.ctx
+    }
+
+    /// The function definitions to register on the assistant.
+    pub fn functions(&self) -> &[FunctionObject] {
+        &self.functions
+    }
+
+    /// Calls the handler of the function `name` with the `args` JSON.
+    /// Errors are returned as the output, so the assistant can handle them.
+    ///
+    /// Note: the handler runs on the blocking threads (e.g., `cargo`), so the
+    ///       caller can still be interrupted (the handler is then cancelled).
+    pub async fn call(&self, name: &str, args: &str) -> String {
+        eprintln!("{} Tool {name}({args})", ico_tool());
+
+        let Some(&handler) = self.handlers.get(name) else {
+            return format!("Error: no function '{name}'");
+        };
+        let args = if args.trim().is_empty() { "{}" } else { args };
+        let args: Value = match serde_json::from_str(args) {
+            Ok(args) => args,
+            Err(err) => return format!("Error: Invalid arguments: {err}"),
+        };
+        let (ctx, _cancel) = self.ctx.for_call();
+        let res = tokio::task::spawn_blocking(move || handler(&ctx, &args))
+            .await
+            .unwrap_or_else(|err| Err(Error::TaskFailed { task: format!("Tool {name}"), cause: err.to_string() }));
+
+        match res {
+            Ok(output) => output,
+            Err(err) => format!("Error: {err}"),
+        }
+    }
+}
+
+// endregion: --- Tools
@@ -1,21 +1,28 @@
+use chrono::DateTime;
 use console::{Style, style, StyledObject};
-use dialoguer::{Input, theme::ColorfulTheme};
+use dialoguer::{Confirm, theme::ColorfulTheme};
 
 use crate::Result;
 
 // region:       --- Prompts
 
-pub fn prompt(text: &str) -> Result<String> {
-    let theme = ColorfulTheme {
+/// Yes/no question (default no).
+pub fn confirm(text: &str) -> Result<bool> {
+    let theme = prompt_theme();
+    let res = Confirm::with_theme(&theme)
+        .with_prompt(text)
+        .default(false)
+        .interact()?;
+
+    Ok(res)
+}
+
+fn prompt_theme() -> ColorfulTheme {
+    ColorfulTheme {
         prompt_style: Style::new().for_stderr().color256(45),
         prompt_prefix: style("♲".to_string()).color256(45).for_stderr(),
         ..ColorfulTheme::default()
-    };
-
-    let input = Input::with_theme(&theme);
-    let res = input.with_prompt(text).interact_text()?;
-
-    Ok(res)
+    }
 }
 
 // endregion:    --- Prompts
@@ -38,6 +45,14 @@ pub fn ico_deleted_ok() -> StyledObject<&'static str> {
     style("⌫").green()
 }
 
+pub fn ico_retry() -> StyledObject<&'static str> {
+    style("↻").yellow()
+}
+
+pub fn ico_tool() -> StyledObject<&'static str> {
+    style("⚙").cyan()
+}
+
 pub fn ico_err() -> StyledObject<&'static str> {
     style("✗").green()
 }
@@ -54,4 +69,29 @@ pub fn txt_res(text: String) -> StyledObject<String> {
     style(text).bright()
 }
 
+/// Formats a size in bytes, e.g., `12.3 KB`.
+pub fn fmt_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB"];
+
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024. && unit < UNITS.len() - 1 {
+        size /= 1024.;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Formats a unix timestamp (seconds) as `YYYY-MM-DD HH:MM` (UTC).
+pub fn fmt_time(secs: i64) -> String {
+    DateTime::from_timestamp(secs, 0)
+        .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_else(|| "-".to_string())
+}
+
 // endregion:    --- Text Output
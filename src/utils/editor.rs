@@ -0,0 +1,122 @@
+//! Composes a message in the external editor (`$VISUAL`, `$EDITOR`, or `vi`).
+
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::process::{Command, ExitStatus};
+
+use tempfile::NamedTempFile;
+
+use crate::utils::files::file_io;
+use crate::{Error, FileOp, Result};
+
+// region:    --- Compose
+
+/// The template line from which the edited file is ignored.
+const TEMPLATE_END: &str = "<!-- rusty-ai: write the message above, this line and the ones below are ignored -->";
+const TEMPLATE_HELP: &str = "<!-- Save and quit the editor to send it (nothing sent if empty).\n     Code in fenced blocks, e.g., ```rust ... ``` -->";
+
+/// Opens the editor on a temporary file with the message template, with the
+/// `quote` (e.g., the last answer) quoted first if any.
+///
+/// Returns the saved message, or `None` if empty (or just the quote).
+pub fn compose_message(quote: Option<&str>) -> Result<Option<String>> {
+    // Note: removed when dropped.
+    let mut file = tempfile::Builder::new().prefix("rusty_ai_message_").suffix(".md").tempfile()?;
+    file.write_all(template(quote).as_bytes())?;
+    file.flush()?;
+
+    let editor = editor_cmd();
+    let status = run_editor(&editor, &file)
+        .map_err(|cause| Error::EditorStart { editor: editor.clone(), cause })?;
+    // Note: read by path, as some editors replace the file.
+    let content = fs::read_to_string(file.path()).map_err(file_io(FileOp::Read, file.path()));
+    if !status.success() {
+        return Err(Error::EditorFailed { editor, status });
+    }
+
+    Ok(message_from(&content?, quote))
+}
+
+fn editor_cmd() -> String {
+    ["VISUAL", "EDITOR"]
+        .iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|editor| !editor.trim().is_empty())
+        .unwrap_or_else(|| if cfg!(windows) { "notepad" } else { "vi" }.to_string())
+}
+
+/// Runs the `editor` command through the shell (so it can have arguments or
+/// a quoted path, e.g., `code --wait`), with the `file` as last argument.
+fn run_editor(editor: &str, file: &NamedTempFile) -> io::Result<ExitStatus> {
+    if cfg!(windows) {
+        Command::new("cmd")
+            .arg("/C")
+            .arg(format!("{editor} \"{}\"", file.path().display()))
+            .status()
+    } else {
+        Command::new("sh")
+            .arg("-c")
+            .arg(format!("{editor} \"$1\""))
+            .arg("sh")
+            .arg(file.path())
+            .status()
+    }
+}
+
+fn template(quote: Option<&str>) -> String {
+    let quoted = quote.map(|quote| format!("{}\n", quote_lines(quote))).unwrap_or_default();
+
+    format!("{quoted}\n\n{TEMPLATE_END}\n{TEMPLATE_HELP}\n")
+}
+
+fn message_from(content: &str, quote: Option<&str>) -> Option<String> {
+    let message = content.split(TEMPLATE_END).next().unwrap_or_default().trim();
+    let only_quote = quote.is_some_and(|quote| message == quote_lines(quote).trim());
+
+    (!message.is_empty() && !only_quote).then(|| message.to_string())
+}
+
+/// Returns the markdown quote of the `text` (`> ` lines).
+fn quote_lines(text: &str) -> String {
+    text.lines()
+        .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {line}") })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// endregion: --- Compose
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Result<T> = core::result::Result<T, Error>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_message_from_template_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let fx_quote = "Use a trait object:\n\n```rust\nBox<dyn Item>\n```";
+        let template = template(Some(fx_quote));
+
+        // -- Exec
+        let edited = template.replacen("\n\n\n", "\n\nWhy not generics?\n\n", 1);
+        let message = message_from(&edited, Some(fx_quote));
+
+        // -- Check
+        assert!(template.starts_with("> Use a trait object:\n>\n> ```rust\n"));
+        assert_eq!(
+            message.as_deref(),
+            Some("> Use a trait object:\n>\n> ```rust\n> Box<dyn Item>\n> ```\n\nWhy not generics?")
+        );
+        assert_eq!(message_from(&template, Some(fx_quote)), None, "only the quote");
+        assert_eq!(message_from(&super::template(None), None), None);
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
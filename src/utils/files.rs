@@ -1,12 +1,13 @@
 use std::{
     fs::{self, File}, 
     path::{Path, PathBuf}, 
-    io::{BufReader, BufWriter, BufRead, Write}, ffi::OsStr
+    io::{self, BufReader, BufWriter, Write}, ffi::OsStr
 };
 use globset::{GlobSet, GlobSetBuilder, Glob};
+use sha2::{Digest, Sha256};
 use walkdir::WalkDir;
 
-use  crate::Result;
+use crate::{Error, FileOp, Result};
 
 // region:    --- Dir Utils
 
@@ -15,7 +16,7 @@ pub fn ensure_dir(dir: &Path) -> Result<bool> {
     if dir.is_dir() {
         Ok(false)
     } else {
-        fs::create_dir_all(dir)?;
+        fs::create_dir_all(dir).map_err(file_io(FileOp::CreateDir, dir))?;
         Ok(true)
     }
 }
@@ -43,8 +44,9 @@ pub fn list_files(
         .into_iter()
         .filter_entry(|e|
             // if dir, check the dir exclude
+            // (relative to `dir`, which can be under an excluded dir)
             if e.file_type().is_dir() {
-                !base_dir_exclude.is_match(e.path())
+                !base_dir_exclude.is_match(e.path().strip_prefix(dir).unwrap_or(e.path()))
             }
             // if file, we apply the globs
             else {
@@ -69,10 +71,25 @@ pub fn list_files(
     Ok(paths.collect())
 }
 
-fn base_dir_exclude_globs() -> Result<GlobSet> {
+/// The dirs never walked (e.g., `.git`, `target`).
+pub fn base_dir_exclude_globs() -> Result<GlobSet> {
     get_glob_set(&["**/.git", "**/target"])
 }
 
+/// Returns the deepest dir containing all the `dirs` (canonicalized, the ones
+/// not existing are skipped).
+pub fn common_dir(dirs: &[PathBuf]) -> Option<PathBuf> {
+    let mut dirs = dirs.iter().filter_map(|dir| dir.canonicalize().ok());
+    let mut common = dirs.next()?;
+    for dir in dirs {
+        while !dir.starts_with(&common) {
+            common = common.parent()?.to_path_buf();
+        }
+    }
+
+    Some(common)
+}
+
 pub fn get_glob_set(globs: &[&str]) -> Result<GlobSet> {
     let mut builder = GlobSetBuilder::new();
     for glob in globs {
@@ -85,20 +102,30 @@ pub fn get_glob_set(globs: &[&str]) -> Result<GlobSet> {
 
 // region:    --- File Utils
 
+/// Returns the `map_err` of the I/O errors of the `op` on `file` (`Error::FileIo`).
+pub fn file_io(op: FileOp, file: &Path) -> impl FnOnce(io::Error) -> Error {
+    let file = file.to_path_buf();
+    move |cause| Error::FileIo { op, file, cause }
+}
 
 fn get_reader(file: &Path) -> Result<BufReader<File>> {
     let Ok(file) = File::open(file) else {
-        return Err(format!("File not found: {}", file.display()).into());
+        return Err(Error::FileNotFound(file.to_path_buf()));
     };
 
     Ok(BufReader::new(file))
 }
 
+/// Returns the SHA-256 (hex) of the `content`.
+pub fn hash_content(content: &[u8]) -> String {
+    Sha256::digest(content).iter().map(|b| format!("{b:02x}")).collect()
+}
+
 pub fn read_to_string(file: &Path) -> Result<String> {
     if !file.is_file() {
-        return Err(format!("File not found: {}", file.display()).into());
+        return Err(Error::FileNotFound(file.to_path_buf()));
     }
-    let content = fs::read_to_string(file)?;
+    let content = fs::read_to_string(file).map_err(file_io(FileOp::Read, file))?;
 
     Ok(content)
 }
@@ -109,9 +136,13 @@ pub fn read_to_string(file: &Path) -> Result<String> {
 
 pub fn load_from_toml<T>(file: impl AsRef<Path>) -> Result<T> 
 where T: serde::de::DeserializeOwned {
-    let content = read_to_string(file.as_ref())?;
+    let file = file.as_ref();
+    let content = read_to_string(file)?;
 
-    Ok(toml::from_str(&content)?)
+    toml::from_str(&content).map_err(|e| Error::Config {
+        file: file.to_path_buf(),
+        cause: e.to_string(),
+    })
 }
 
 pub fn load_from_json<T>(file: impl AsRef<Path>) -> Result<T> 
@@ -124,8 +155,7 @@ pub fn save_to_json<T>(file: impl AsRef<Path>, data: &T) -> Result<()>
 where T: serde::Serialize {
     let file = file.as_ref();
 
-    let file = File::create(file)
-        .map_err(|e| format!("Cannot create file '{:?}': {}", file, e))?;
+    let file = File::create(file).map_err(file_io(FileOp::Write, file))?;
 
     serde_json::to_writer_pretty(file, data)?;
 
@@ -136,24 +166,24 @@ where T: serde::Serialize {
 // region:       --- File Bundler
 
 pub fn bundle_to_file(files: Vec<PathBuf>, dst_file: &Path) -> Result<()> {
-    let mut writer = BufWriter::new(File::create(dst_file)?);
+    let write_err = || file_io(FileOp::Write, dst_file);
+    let mut writer = BufWriter::new(File::create(dst_file).map_err(write_err())?);
 
     for file in files {
         if !file.is_file() {
-            return Err(format!("Cannot bundle '{:?}' is not a file.", file).into());
+            return Err(Error::BundleSource(file));
         }
 
-        let reader = get_reader(&file)?;
+        let content = read_to_string(&file)?;
 
-        writeln!(writer, "\n// ==== file path: {}\n", file.to_string_lossy())?;
+        writeln!(writer, "\n// ==== file path: {}\n", file.to_string_lossy()).map_err(write_err())?;
 
-        for line in reader.lines() {
-            let line = line?;
-            writeln!(writer, "{}", line)?;
+        for line in content.lines() {
+            writeln!(writer, "{}", line).map_err(write_err())?;
         }
-        write!(writer, "\n\n")?;
+        write!(writer, "\n\n").map_err(write_err())?;
     }
-    writer.flush()?;
+    writer.flush().map_err(write_err())?;
 
     Ok(())
 }
@@ -166,6 +196,7 @@ pub fn bundle_to_file(files: Vec<PathBuf>, dst_file: &Path) -> Result<()> {
 /// the `&str` when ok, and when none or err, returns ""
 pub trait XFile {
     fn x_file_name(&self) -> &str;
+    fn x_file_stem(&self) -> &str;
     fn _x_extension(&self) -> &str;
 }
 
@@ -174,6 +205,10 @@ impl XFile for Path {
         self.file_name().and_then(OsStr::to_str).unwrap_or("")
     }
 
+    fn x_file_stem(&self) -> &str {
+        self.file_stem().and_then(OsStr::to_str).unwrap_or("")
+    }
+
     fn _x_extension(&self) -> &str {
         self.extension().and_then(OsStr::to_str).unwrap_or("")
     }
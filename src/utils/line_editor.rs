@@ -0,0 +1,187 @@
+//! Line editing prompt (rustyline): history persisted in a file, reverse
+//! search (Ctrl-R), multi-line input, and tab completion of the `:` commands.
+//!
+//! The input goes on (Enter adds a new line) while a code block is open
+//! (```` ``` ````), or when the line ends with `\` (removed from the message).
+
+use std::borrow::Cow;
+use std::path::PathBuf;
+
+use console::style;
+use rustyline::completion::{Completer, Pair};
+use rustyline::error::ReadlineError;
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::history::FileHistory;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{CompletionType, Config, Context, Editor, Helper};
+
+use crate::utils::md::fence_info;
+use crate::Result;
+
+// region:    --- Types
+
+const MAX_HISTORY: usize = 1000;
+
+pub struct LineEditor {
+    editor: Editor<PromptHelper, FileHistory>,
+    history_file: PathBuf,
+}
+
+/// Completes the `:` commands and tells when the input goes on.
+struct PromptHelper {
+    commands: Vec<String>,
+}
+
+// endregion: --- Types
+
+// region:    --- LineEditor
+
+impl LineEditor {
+    /// - `history_file` loaded if it exists, and appended after each input.
+    /// - `commands` the completions of the inputs starting with `:`.
+    pub fn new(history_file: PathBuf, commands: Vec<String>) -> Result<Self> {
+        let config = Config::builder()
+            .max_history_size(MAX_HISTORY)?
+            .history_ignore_dups(true)?
+            .history_ignore_space(true)
+            .completion_type(CompletionType::List)
+            .build();
+        let mut editor = Editor::with_config(config)?;
+        editor.set_helper(Some(PromptHelper { commands }));
+        if history_file.is_file() {
+            editor.load_history(&history_file)?;
+        }
+
+        Ok(Self { editor, history_file })
+    }
+
+    /// Returns the input (possibly multi-line), or `None` on Ctrl-C or Ctrl-D.
+    pub fn read(&mut self, text: &str) -> Result<Option<String>> {
+        let input = match self.editor.readline(&format!("♲ {text} › ")) {
+            Ok(input) => input,
+            Err(ReadlineError::Interrupted | ReadlineError::Eof) => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+
+        if !input.trim().is_empty() {
+            self.editor.add_history_entry(input.as_str())?;
+            self.editor.append_history(&self.history_file)?;
+        }
+
+        Ok(Some(join_continued(&input)))
+    }
+}
+
+// endregion: --- LineEditor
+
+// region:    --- PromptHelper
+
+impl Helper for PromptHelper {}
+
+impl Hinter for PromptHelper {
+    type Hint = String;
+}
+
+impl Highlighter for PromptHelper {
+    fn highlight_prompt<'b, 's: 'b, 'p: 'b>(&'s self, prompt: &'p str, _default: bool) -> Cow<'b, str> {
+        Cow::Owned(style(prompt).color256(45).to_string())
+    }
+}
+
+impl Completer for PromptHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<Pair>)> {
+        Ok((0, self.completions(&line[..pos])))
+    }
+}
+
+impl Validator for PromptHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> rustyline::Result<ValidationResult> {
+        if is_incomplete(ctx.input()) {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
+
+impl PromptHelper {
+    /// Returns the commands starting with the `prefix` (a `:` command).
+    fn completions(&self, prefix: &str) -> Vec<Pair> {
+        if !prefix.starts_with(':') {
+            return Vec::new();
+        }
+
+        self.commands
+            .iter()
+            .filter(|cmd| cmd.starts_with(prefix))
+            .map(|cmd| Pair {
+                display: cmd.trim_end().to_string(),
+                replacement: cmd.to_string(),
+            })
+            .collect()
+    }
+}
+
+// endregion: --- PromptHelper
+
+// region:    --- Support
+
+/// True when a code block is still open, or the last line ends with `\`.
+fn is_incomplete(input: &str) -> bool {
+    let num_fences = input.lines().filter(|line| fence_info(line).is_some()).count();
+
+    num_fences % 2 == 1 || input.ends_with('\\')
+}
+
+/// Removes the `\` of the continued lines (keeping the new lines).
+fn join_continued(input: &str) -> String {
+    input.replace("\\\n", "\n")
+}
+
+// endregion: --- Support
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Result<T> = core::result::Result<T, Error>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_completions_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let helper = PromptHelper {
+            commands: [":check ", ":check test", ":conv new ", ":convs"].map(String::from).to_vec(),
+        };
+
+        // -- Exec
+        let conv: Vec<String> = helper.completions(":con").into_iter().map(|p| p.replacement).collect();
+        let check: Vec<String> = helper.completions(":check t").into_iter().map(|p| p.replacement).collect();
+
+        // -- Check
+        assert_eq!(conv, [":conv new ", ":convs"]);
+        assert_eq!(check, [":check test"]);
+        assert!(helper.completions("con").is_empty(), "only the : commands");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_line_input_ok() -> Result<()> {
+        // -- Check
+        assert!(is_incomplete("Why:\n```rust\nfn main() {}"));
+        assert!(!is_incomplete("Why:\n```rust\nfn main() {}\n```"));
+        assert!(is_incomplete("first line \\"));
+        assert!(!is_incomplete("first line \\\nsecond line"));
+        assert_eq!(join_continued("first line \\\nsecond line"), "first line \nsecond line");
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
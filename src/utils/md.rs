@@ -0,0 +1,98 @@
+//! Markdown of the answers: fenced code blocks (see `render` for the display).
+
+// region:    --- Code Blocks
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CodeBlock {
+    /// The info string of the opening fence (e.g., `rust`), if any.
+    pub lang: Option<String>,
+    /// Without the fences (ends with a new line).
+    pub content: String,
+}
+
+/// Returns the fenced (```` ``` ````) code blocks of the markdown `text`,
+/// in order (block N being `blocks[N - 1]`).
+/// Note: an unclosed block (e.g., interrupted answer) is still returned.
+pub fn code_blocks(text: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut current: Option<CodeBlock> = None;
+
+    for line in text.lines() {
+        let fence = fence_info(line);
+        match (current.as_mut(), fence) {
+            (Some(_), Some("")) => blocks.extend(current.take()),
+            (Some(block), _) => {
+                block.content.push_str(line);
+                block.content.push('\n');
+            }
+            (None, Some(lang)) => {
+                current = Some(CodeBlock {
+                    lang: (!lang.is_empty()).then(|| lang.to_string()),
+                    content: String::new(),
+                });
+            }
+            (None, None) => (),
+        }
+    }
+    blocks.extend(current);
+
+    blocks
+}
+
+/// Returns the info string of a fence line (empty for a closing fence).
+pub fn fence_info(line: &str) -> Option<&str> {
+    line.trim_start().strip_prefix("```").map(str::trim)
+}
+
+// endregion: --- Code Blocks
+
+// region:    --- Format
+
+/// Returns the one line summary of the `blocks`, e.g., `[1] rust (12 lines), [2] sh (1 line)`.
+pub fn fmt_blocks(blocks: &[CodeBlock]) -> String {
+    blocks
+        .iter()
+        .enumerate()
+        .map(|(idx, block)| {
+            let num_lines = block.content.lines().count();
+            let s = if num_lines == 1 { "" } else { "s" };
+            let lang = block.lang.as_deref().unwrap_or("text");
+            format!("[{}] {lang} ({num_lines} line{s})", idx + 1)
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// endregion: --- Format
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Result<T> = core::result::Result<T, Error>; // For tests.
+
+    use super::*;
+
+    const FX_ANSWER: &str = "Use a trait object, this way the function accepts any type implementing the trait:\n\n```rust\nfn run(items: &[Box<dyn Item>]) {\n    for item in items {\n        item.exec(); // a long comment which must never be wrapped even when it goes past the width\n    }\n}\n```\n\nThen:\n```\ncargo test\n```";
+
+    #[test]
+    fn test_code_blocks_ok() -> Result<()> {
+        // -- Exec
+        let blocks = code_blocks(FX_ANSWER);
+        let unclosed = code_blocks("Partial:\n```toml\n[package]\n");
+
+        // -- Check
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].lang.as_deref(), Some("rust"));
+        assert!(blocks[0].content.starts_with("fn run(items: &[Box<dyn Item>]) {\n    for item"));
+        assert!(blocks[0].content.ends_with("}\n"));
+        assert_eq!(blocks[1], CodeBlock { lang: None, content: "cargo test\n".to_string() });
+        assert_eq!(unclosed[0].content, "[package]\n");
+        assert_eq!(fmt_blocks(&blocks), "[1] rust (5 lines), [2] text (1 line)");
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
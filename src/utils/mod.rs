@@ -2,5 +2,9 @@
 
 pub mod files;
 pub mod cli;
+pub mod editor;
+pub mod line_editor;
+pub mod md;
+pub mod render;
 
 // endregion: --- Modules
\ No newline at end of file
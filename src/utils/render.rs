@@ -0,0 +1,329 @@
+//! Terminal rendering of the markdown answers: headings, lists, quotes, inline
+//! code and bold, code blocks highlighted (syntect), prose wrapped to the
+//! terminal width.
+//!
+//! Plain mode (`--plain`, `NO_COLOR`, or stdout not a terminal) only wraps the
+//! prose, the code blocks being untouched.
+
+use std::env;
+use std::io::{self, IsTerminal};
+
+use console::{style, Term};
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::as_24_bit_terminal_escaped;
+use textwrap::{wrap, Options};
+
+use crate::utils::md::fence_info;
+
+// region:    --- Types
+
+const MIN_WIDTH: usize = 40;
+const MAX_WIDTH: usize = 100;
+const THEME: &str = "base16-ocean.dark";
+
+pub struct Renderer {
+    plain: bool,
+    /// Prose wrapping width.
+    width: usize,
+    syntaxes: SyntaxSet,
+    theme: Theme,
+}
+
+/// Renders an answer line by line (e.g., as it is streamed), keeping the
+/// code block state.
+pub struct LineRenderer<'a> {
+    renderer: &'a Renderer,
+    /// In a code block (with its highlighter, if not plain).
+    code: Option<Option<HighlightLines<'a>>>,
+    num_block: usize,
+    /// The streamed text not yet ending with a new line.
+    pending: String,
+}
+
+// endregion: --- Types
+
+// region:    --- Renderer
+
+impl Renderer {
+    /// Plain if `plain`, `NO_COLOR` is set, or stdout is not a terminal.
+    /// `plain` and `NO_COLOR` also disable the colors of all the output.
+    pub fn new(plain: bool) -> Self {
+        let no_color = plain || env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        if no_color {
+            console::set_colors_enabled(false);
+            console::set_colors_enabled_stderr(false);
+        }
+        let plain = no_color || !io::stdout().is_terminal();
+
+        let width = Term::stdout()
+            .size_checked()
+            .map_or(80, |(_, cols)| cols as usize)
+            .clamp(MIN_WIDTH, MAX_WIDTH);
+
+        Self::with_width(plain, width)
+    }
+
+    fn with_width(plain: bool, width: usize) -> Self {
+        // Note: the syntaxes and themes are only loaded when used.
+        let (syntaxes, theme) = if plain {
+            (SyntaxSet::new(), Theme::default())
+        } else {
+            let theme = ThemeSet::load_defaults().themes.remove(THEME).unwrap_or_default();
+            (SyntaxSet::load_defaults_newlines(), theme)
+        };
+
+        Self {
+            plain,
+            width,
+            syntaxes,
+            theme,
+        }
+    }
+
+    pub fn is_plain(&self) -> bool {
+        self.plain
+    }
+
+    /// Returns the rendered `text`, the opening fences being numbered
+    /// (e.g., ```` ```rust [1] ````, see `md::code_blocks`).
+    pub fn render(&self, text: &str) -> String {
+        let mut lines = self.lines();
+        text.lines().map(|line| lines.render_line(line)).collect::<Vec<_>>().join("\n")
+    }
+
+    pub fn lines(&self) -> LineRenderer<'_> {
+        LineRenderer {
+            renderer: self,
+            code: None,
+            num_block: 0,
+            pending: String::new(),
+        }
+    }
+}
+
+// endregion: --- Renderer
+
+// region:    --- LineRenderer
+
+impl<'a> LineRenderer<'a> {
+    /// Returns the rendered complete lines of the text so far (with their
+    /// new line), the last incomplete one being kept for the next deltas.
+    pub fn push(&mut self, delta: &str) -> String {
+        self.pending.push_str(delta);
+        let Some(end) = self.pending.rfind('\n') else {
+            return String::new();
+        };
+
+        let complete: String = self.pending.drain(..=end).collect();
+        complete.lines().map(|line| format!("{}\n", self.render_line(line))).collect()
+    }
+
+    /// Returns the rendered last incomplete line (if any).
+    pub fn finish(&mut self) -> String {
+        let pending = std::mem::take(&mut self.pending);
+        if pending.is_empty() {
+            pending
+        } else {
+            self.render_line(&pending)
+        }
+    }
+
+    fn render_line(&mut self, line: &str) -> String {
+        let fence = fence_info(line);
+        let renderer = self.renderer;
+
+        match (self.code.as_mut(), fence) {
+            // -- Code block end
+            (Some(_), Some("")) => {
+                self.code = None;
+                style(line).dim().to_string()
+            }
+            // -- Code block line
+            (Some(highlighter), _) => match highlighter {
+                Some(highlighter) => {
+                    let line_nl = format!("{line}\n");
+                    match highlighter.highlight_line(&line_nl, &renderer.syntaxes) {
+                        Ok(ranges) => {
+                            let escaped = as_24_bit_terminal_escaped(&ranges, false);
+                            format!("{}\x1b[0m", escaped.trim_end_matches('\n'))
+                        }
+                        Err(_) => line.to_string(),
+                    }
+                }
+                None => line.to_string(),
+            },
+            // -- Code block start
+            (None, Some(lang)) => {
+                self.num_block += 1;
+                self.code = Some((!renderer.plain).then(|| {
+                    let syntax = renderer
+                        .syntaxes
+                        .find_syntax_by_token(lang)
+                        .unwrap_or_else(|| renderer.syntaxes.find_syntax_plain_text());
+                    HighlightLines::new(syntax, &renderer.theme)
+                }));
+                style(format!("{line} [{}]", self.num_block)).dim().to_string()
+            }
+            // -- Prose
+            (None, None) if renderer.plain => wrap_line(line, "", "", renderer.width),
+            (None, None) => render_prose(line, renderer.width),
+        }
+    }
+}
+
+// endregion: --- LineRenderer
+
+// region:    --- Prose
+
+fn render_prose(line: &str, width: usize) -> String {
+    let text = line.trim_start();
+    let indent = &line[..line.len() - text.len()];
+
+    // -- Heading
+    if let Some((hashes, title)) = text.split_once(' ').filter(|(h, _)| is_heading(h)) {
+        let title = style(title.trim()).bold().cyan();
+        return if hashes.len() == 1 { title.underlined().to_string() } else { title.to_string() };
+    }
+
+    // -- Rule
+    if ["---", "***", "___"].contains(&text) {
+        return style("─".repeat(width)).dim().to_string();
+    }
+
+    // -- Quote
+    if let Some(quote) = text.strip_prefix('>') {
+        let bar = format!("{indent}{} ", style("│").dim());
+        return wrap_line(&style_inline(quote.trim_start()), &bar, &bar, width);
+    }
+
+    // -- List item
+    if let Some(item) = ["- ", "* ", "+ "].iter().find_map(|m| text.strip_prefix(m)) {
+        let bullet = format!("{indent}{} ", style("•").cyan());
+        return wrap_line(&style_inline(item), &bullet, &format!("{indent}  "), width);
+    }
+    if let Some((num, item)) = text.split_once(". ").filter(|(n, _)| is_number(n)) {
+        let marker = format!("{indent}{num}. ");
+        let next_indent = " ".repeat(marker.len());
+        return wrap_line(&style_inline(item), &style(&marker).cyan().to_string(), &next_indent, width);
+    }
+
+    wrap_line(&style_inline(text), indent, indent, width)
+}
+
+/// Wraps the `text` (ANSI escapes not counted) at `width`.
+/// Note: the indent of a plain `text` line is kept on its wrapped lines.
+fn wrap_line(text: &str, first_indent: &str, next_indent: &str, width: usize) -> String {
+    let text_start = text.trim_start();
+    let own_indent = &text[..text.len() - text_start.len()];
+    let (first_indent, next_indent) = if first_indent.is_empty() && next_indent.is_empty() {
+        (own_indent, own_indent)
+    } else {
+        (first_indent, next_indent)
+    };
+
+    let options = Options::new(width)
+        .initial_indent(first_indent)
+        .subsequent_indent(next_indent);
+    match wrap(text_start, options).as_slice() {
+        [] => String::new(),
+        lines => lines.join("\n"),
+    }
+}
+
+/// Styles the inline `code` and **bold** of a prose line.
+fn style_inline(text: &str) -> String {
+    text.split('`')
+        .enumerate()
+        .map(|(idx, part)| {
+            if idx % 2 == 1 {
+                style(part).yellow().to_string()
+            } else {
+                part.split("**")
+                    .enumerate()
+                    .map(|(idx, part)| if idx % 2 == 1 { style(part).bold().to_string() } else { part.to_string() })
+                    .collect()
+            }
+        })
+        .collect()
+}
+
+fn is_heading(hashes: &str) -> bool {
+    (1..=6).contains(&hashes.len()) && hashes.chars().all(|c| c == '#')
+}
+
+fn is_number(num: &str) -> bool {
+    !num.is_empty() && num.chars().all(|c| c.is_ascii_digit())
+}
+
+// endregion: --- Prose
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Result<T> = core::result::Result<T, Error>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_render_plain_wraps_prose_only_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let renderer = Renderer::with_width(true, 30);
+        let fx_text = "The error comes from the borrow of `conv` kept across the await:\n\n```rust\nlet conv = &mut self.conv; // a comment longer than the width, never wrapped\n```\n  - indented item which goes past the width\n```\n```";
+
+        // -- Exec
+        let res = renderer.render(fx_text);
+
+        // -- Check
+        let lines: Vec<&str> = res.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "The error comes from the",
+                "borrow of `conv` kept across",
+                "the await:",
+                "",
+                "```rust [1]",
+                "let conv = &mut self.conv; // a comment longer than the width, never wrapped",
+                "```",
+                "  - indented item which goes",
+                "  past the width",
+                "``` [2]",
+                "```",
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_render_stream_same_as_whole_ok() -> Result<()> {
+        // -- Setup & Fixtures
+        let renderer = Renderer::with_width(false, 40);
+        let fx_text = "# Title\n\n- a **bold** item with `code`\n1. first\n> quoted\n\n```rust\nlet a = 1;\n```\nend";
+
+        // -- Exec
+        let whole = renderer.render(fx_text);
+        let mut lines = renderer.lines();
+        let mut streamed: String = fx_text
+            .split_inclusive(' ')
+            .map(|delta| lines.push(delta))
+            .collect();
+        streamed.push_str(&lines.finish());
+
+        // -- Check
+        assert_eq!(streamed, whole);
+        let lines: Vec<String> = whole.lines().map(|l| console::strip_ansi_codes(l).to_string()).collect();
+        assert_eq!(
+            lines,
+            ["Title", "", "• a bold item with code", "1. first", "│ quoted", "", "```rust [1]", "let a = 1;", "```", "end"]
+        );
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests